@@ -0,0 +1,203 @@
+#[macro_use]
+extern crate criterion;
+extern crate my_bot;
+
+use criterion::{BatchSize, BenchmarkId, Criterion};
+use my_bot::action::{Action, MergedAction};
+use my_bot::fixture::{Fixture, Frames};
+use my_bot::fallback::Fallback;
+use my_bot::hlt::*;
+use my_bot::profile::Profiles;
+use my_bot::replay;
+use my_bot::scenario::move_of;
+use my_bot::stats::Stats;
+use my_bot::timeline::{self, Timeline};
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+// Compare against an earlier commit by running `cargo bench -- --save-baseline before` there,
+// then `cargo bench -- --baseline before` on the commit under test
+
+const LOOKAHEAD: usize = 40;
+/// Recorded streams in golden/ and the turn each is advanced to, measured at their map size
+const RECORDED: [(&str, usize); 2] = [("duel-32", 45), ("duel-40", 100)];
+/// Map sizes with no recorded stream and total ship counts, measured on synthetic fixtures
+const SIZES: [usize; 2] = [48, 64];
+const SHIPS: [usize; 3] = [20, 60, 120];
+
+/// Paths ships are following, as the bot keeps them between turns
+type Paths = HashMap<ShipId, VecDeque<Action>>;
+
+/// Recorded games at their benchmark turn, by stream name, with the paths the bot planned into it
+fn recorded() -> Vec<(&'static str, Game, Paths)> {
+    RECORDED.iter()
+        .map(|&(name, turn)| {
+            let (game, bot) = replay::advance(name, turn);
+            let paths = bot.paths().clone();
+            (name, game, paths)
+        })
+        .collect()
+}
+
+/// Fixture game one turn on from a planned turn, with the paths its ships are following
+fn planned(size: usize, ships: usize) -> (Game, Paths) {
+    let mut game = Fixture::new(size, size, 2, ships / 2).game();
+    let mut paths = HashMap::new();
+    let stats = Stats::new();
+
+    let commands = {
+        let mut timeline = Timeline::from(&game, Vec::new(), &mut paths, &Profiles::new(), &HashMap::new(), &stats);
+        timeline.path_ships(&mut paths, &mut Fallback::new(), &stats)
+    };
+
+    // Move the ships as commanded so the paths replay from where they left off
+    let torus = game.map.torus;
+    for ship in game.ships.values_mut().filter(|ship| ship.owner == PlayerId(0)) {
+        if let Some(dir) = move_of(&commands, ship.id) {
+            ship.position = torus.normalize(ship.position.directional_offset(dir));
+        }
+    }
+    game.turn_number += 1;
+
+    (game, paths)
+}
+
+fn timeline_from(c: &mut Criterion) {
+    let mut group = c.benchmark_group("timeline_from");
+    group.sample_size(10);
+
+    for (name, game, paths) in recorded() {
+        let ships = game.players[0].ship_ids.len();
+
+        group.bench_with_input(BenchmarkId::new(name, ships), &ships, |b, _| b.iter_batched(
+            || paths.clone(),
+            |mut paths| Timeline::from(&game, Vec::new(), &mut paths, &Profiles::new(), &HashMap::new(), &Stats::new()),
+            BatchSize::LargeInput,
+        ));
+    }
+
+    for &size in &SIZES {
+        for &ships in &SHIPS {
+            let (game, paths) = planned(size, ships);
+
+            group.bench_with_input(BenchmarkId::new(format!("{}x{}", size, size), ships), &ships, |b, _| b.iter_batched(
+                || paths.clone(),
+                |mut paths| Timeline::from(&game, Vec::new(), &mut paths, &Profiles::new(), &HashMap::new(), &Stats::new()),
+                BatchSize::LargeInput,
+            ));
+        }
+    }
+
+    group.finish();
+}
+
+fn path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("path");
+
+    let recorded = recorded().into_iter().map(|(name, game, _)| (name.to_string(), game));
+    let fixtures = SIZES.iter().map(|&size| (format!("{}x{}", size, size), Fixture::new(size, size, 2, 30).game()));
+
+    for (name, game) in recorded.chain(fixtures) {
+        let mut timeline = Timeline::from(&game, Vec::new(), &mut HashMap::new(), &Profiles::new(), &HashMap::new(), &Stats::new());
+
+        let ship = &game.ships[&game.players[0].ship_ids[0]];
+        let target = timeline.target_pos_t(ship.id, ship.position);
+
+        group.bench_function(name, |b| b.iter(|| {
+            let action = MergedAction::new(ship.id, ship.position, ship.halite);
+            timeline.path(action, 0, target, LOOKAHEAD)
+        }));
+    }
+
+    group.finish();
+}
+
+fn actions(c: &mut Criterion) {
+    let fixture = Fixture::new(64, 64, 2, 30);
+    let game = fixture.game();
+    let mut state = fixture.state();
+    for ship in game.ships.values().filter(|ship| ship.owner == game.my_id) {
+        state.add_ship(ship);
+    }
+
+    let ship = &game.ships[&game.players[0].ship_ids[0]];
+    let merged = MergedAction::new(ship.id, ship.position, ship.halite);
+
+    c.bench_function("state_actions 64x64", |b| b.iter(|| state.actions(&merged, false)));
+}
+
+fn inspiration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("inspiration");
+
+    for &ships in &SHIPS {
+        let mut state = Fixture::new(64, 64, 2, ships / 2).state();
+        group.bench_with_input(BenchmarkId::new("64x64", ships), &ships, |b, _| b.iter(|| state.forecast_inspiration()));
+    }
+
+    group.finish();
+}
+
+fn richness(c: &mut Criterion) {
+    let mut group = c.benchmark_group("richness");
+
+    let recorded = recorded().into_iter().map(|(name, game, _)| (name.to_string(), game));
+    let fixtures = SIZES.iter().map(|&size| (format!("{}x{}", size, size), Fixture::new(size, size, 2, 0).game()));
+
+    for (name, game) in recorded.chain(fixtures) {
+        group.bench_function(name, |b| b.iter(|| timeline::richness(&game, &HashMap::new())));
+    }
+
+    group.finish();
+}
+
+fn collect_moves(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collect_moves");
+
+    for &resolver in &[Resolver::Signal, Resolver::Matching] {
+        let name = if resolver == Resolver::Signal { "signal" } else { "matching" };
+
+        for &ships in &SHIPS {
+            let game = Fixture::new(64, 64, 2, ships / 2).game();
+            let shipyard = game.players[0].shipyard.position;
+
+            // Every ship heading home, so they crowd around the shipyard
+            let navi = || {
+                let mut nav = Navi::new(game.map.width, game.map.height);
+                nav.update_frame(&game);
+                nav.resolver = resolver;
+
+                for ship in game.ships.values().filter(|ship| ship.owner == game.my_id) {
+                    let dir = game.map.torus.directions(ship.position, shipyard).first().cloned().unwrap_or(Direction::Still);
+                    nav.nav(ship.id, ship.position, dir);
+                }
+
+                nav
+            };
+
+            group.bench_with_input(BenchmarkId::new(name, ships), &ships, |b, _| b.iter_batched(
+                navi,
+                |mut nav| nav.collect_moves(),
+                BatchSize::SmallInput,
+            ));
+        }
+    }
+
+    group.finish();
+}
+
+fn turn(c: &mut Criterion) {
+    // Few enough ships to path them all within the turn budget
+    let game = Fixture::new(64, 64, 2, 5).game();
+
+    let mut group = c.benchmark_group("turn");
+    group.sample_size(10);
+    group.bench_function("path_ships 64x64", |b| b.iter(|| {
+        let mut paths = HashMap::new();
+        let mut timeline = Timeline::from(&game, Vec::new(), &mut paths, &Profiles::new(), &HashMap::new(), &Stats::new());
+        timeline.path_ships(&mut paths, &mut Fallback::new(), &Stats::new())
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, timeline_from, path, actions, inspiration, richness, collect_moves, turn);
+criterion_main!(benches);
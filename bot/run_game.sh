@@ -4,21 +4,40 @@ set -e
 
 [ -z "$SIZE" ] && SIZE=32
 
+# Where the bots write their logs and how they name them, as read by the bots themselves
+LOG_DIR="${BOT_LOG_DIR:-.}"
+LOG_NAME="${BOT_LOG_NAME:-bot-{id\}}"
+
+# Logs older than this were left by earlier runs
+STARTED=$(mktemp)
+trap 'rm -f "$STARTED"' EXIT
+
+# RECORD=golden/NAME.input saves the engine input to the first bot, for the golden replays
+MY_BOT="./target/release/my_bot"
+[ -n "$RECORD" ] && MY_BOT="tee $RECORD | $MY_BOT"
+
 if [ "$1" == "-d" ]; then
   cargo build
   ./halite --replay-directory replays/ -vvv --width $SIZE --height $SIZE "RUST_BACKTRACE=1 ./target/debug/my_bot" "RUST_BACKTRACE=1 ./target/debug/my_bot"
 elif [ "$1" == "-o" ]; then
   cargo build --release
-  ./halite --replay-directory replays/ -vvv --width $SIZE --height $SIZE "./target/release/my_bot" "./$2"
+  ./halite --replay-directory replays/ -vvv --width $SIZE --height $SIZE "$MY_BOT" "./$2"
 elif [ "$1" == "-s" ]; then
   cargo build --release
-  ./halite --replay-directory replays/ -vvv -s $2 --width $SIZE --height $SIZE "./target/release/my_bot" "./$3"
+  ./halite --replay-directory replays/ -vvv -s $2 --width $SIZE --height $SIZE "$MY_BOT" "./$3"
 elif [ "$1" == "-m" ]; then
-  ./halite --replay-directory replays/ -vvv --width $SIZE --height $SIZE "./target/release/my_bot" "./$2" "./$3" "./$4" 
+  ./halite --replay-directory replays/ -vvv --width $SIZE --height $SIZE "$MY_BOT" "./$2" "./$3" "./$4" 
 else
   cargo build --release
-  ./halite --replay-directory replays/ -vvv --width $SIZE --height $SIZE "./target/release/my_bot"
+  ./halite --replay-directory replays/ -vvv --width $SIZE --height $SIZE "$MY_BOT"
 fi
 
-cat bot-0.log >bot.log
-tail -n +2 bot-1.log >>bot.log
+# Merge the overlay logs this run's bots wrote into one for fluorine
+PATTERN="${LOG_NAME//\{id\}/*}"
+PATTERN="${PATTERN//\{seed\}/*}"
+PATTERN="${PATTERN//\{time\}/*}"
+mapfile -t LOGS < <(find "$LOG_DIR" -maxdepth 1 -name "$PATTERN.log" -newer "$STARTED" | sort)
+
+if [ ${#LOGS[@]} -gt 0 ]; then
+  cargo run --release --quiet --bin merge_logs -- "$LOG_DIR/bot.log" "${LOGS[@]}"
+fi
@@ -16,6 +16,19 @@ impl Action {
     }
 }
 
+/// Parts of a path's cost, which add up to its total
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Breakdown {
+    /// Halite burnt moving
+    pub moves: i32,
+    /// Credit for halite mined, negative
+    pub mining: i32,
+    /// Penalty for moving next to enemies
+    pub risk: i32,
+    /// Penalty for crashing into a dropoff at the end of the game
+    pub end_game: i32,
+}
+
 #[derive(Clone)]
 pub struct MergedAction {
     pub ship_id: ShipId,
@@ -26,6 +39,7 @@ pub struct MergedAction {
     pub risk: bool,
     pub mined: im::HashMap<Position, usize>,
     pub cost: i32,
+    pub breakdown: Breakdown,
 }
 
 impl MergedAction {
@@ -39,6 +53,7 @@ impl MergedAction {
             risk: false,
             mined: im::HashMap::new(),
             cost: 0,
+            breakdown: Breakdown::default(),
         }
     }
 
@@ -52,6 +67,7 @@ impl MergedAction {
             risk: false,
             mined: im::HashMap::new(),
             cost: 0,
+            breakdown: Breakdown::default(),
         }
     }
 }
@@ -0,0 +1,42 @@
+extern crate my_bot;
+
+use my_bot::replay::{self, GOLDEN_DIR};
+use std::env;
+use std::process::exit;
+
+/// Replay every recorded input stream and compare the bot's commands against the goldens,
+/// or with --refresh overwrite the goldens after an intended change of strategy
+fn main() {
+    let refresh = match env::args().nth(1).as_deref() {
+        None => false,
+        Some("--refresh") => true,
+        Some(_) => {
+            eprintln!("Usage: golden [--refresh]");
+            eprintln!("Streams are NAME.input files in {}, recorded with RECORD=FILE ./run_game.sh", GOLDEN_DIR);
+            exit(1)
+        }
+    };
+
+    let mut failed = false;
+    for name in replay::streams() {
+        if refresh {
+            replay::refresh(&name);
+            println!("{}: refreshed", name);
+            continue;
+        }
+
+        let differences = replay::check(&name);
+        if differences.is_empty() {
+            println!("{}: ok", name);
+        } else {
+            failed = true;
+            for difference in differences {
+                println!("{}: {}", name, difference);
+            }
+        }
+    }
+
+    if failed {
+        exit(1);
+    }
+}
@@ -0,0 +1,27 @@
+extern crate my_bot;
+
+use my_bot::hlt::Log;
+use std::env;
+use std::fs;
+use std::process::exit;
+
+/// Merge per-bot logs into one file for fluorine: merge_logs OUTPUT LOG...
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("Usage: merge_logs OUTPUT LOG...");
+        exit(1);
+    }
+
+    let logs: Vec<String> = args[1..].iter()
+        .map(|path| fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Couldn't read {}: {}", path, e);
+            exit(1)
+        }))
+        .collect();
+
+    fs::write(&args[0], Log::merge(&logs)).unwrap_or_else(|e| {
+        eprintln!("Couldn't write {}: {}", args[0], e);
+        exit(1)
+    });
+}
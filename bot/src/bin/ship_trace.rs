@@ -0,0 +1,48 @@
+extern crate my_bot;
+
+use my_bot::trace::parse_line;
+use std::env;
+use std::fs;
+use std::process::exit;
+
+fn usage() -> ! {
+    eprintln!("Usage: ship_trace LOG SHIP [TURN | FIRST..LAST]");
+    eprintln!("LOG is a bot's diagnostic log written with BOT_LOG=trace or planner=trace");
+    exit(1)
+}
+
+/// Print the decision trace of one ship from a diagnostic log, optionally over a range of turns
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 || args.len() > 3 {
+        usage();
+    }
+
+    let ship_id: usize = args[1].parse().unwrap_or_else(|_| usage());
+    let (first, last) = match args.get(2) {
+        None => (0, usize::MAX),
+        Some(range) => {
+            let mut bounds = range.splitn(2, "..");
+            let first = bounds.next().filter(|s| !s.is_empty()).map(|s| s.parse().unwrap_or_else(|_| usage()));
+            let last = match bounds.next() {
+                Some(s) if !s.is_empty() => s.parse().unwrap_or_else(|_| usage()),
+                Some(_) => usize::MAX,
+                None => first.unwrap_or(usize::MAX),
+            };
+            (first.unwrap_or(0), last)
+        }
+    };
+
+    let log = fs::read_to_string(&args[0]).unwrap_or_else(|e| {
+        eprintln!("Couldn't read {}: {}", args[0], e);
+        exit(1)
+    });
+
+    for line in log.lines() {
+        if let Some((turn, id)) = parse_line(line) {
+            if id.0 == ship_id && turn >= first && turn <= last {
+                println!("{}", line);
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use hlt::*;
+use action::Action;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use timeline::Timeline;
+use stats::Stats;
+use profile::Profiles;
+use collision::Collisions;
+use fallback::Fallback;
+use divergence::Divergence;
+use metrics::Metrics;
+
+/// Everything the bot keeps from one turn to the next
+pub struct Bot {
+    total_halite: usize,
+    paths: HashMap<ShipId, VecDeque<Action>>,
+    pub stats: Stats,
+    collisions: Collisions,
+    profiles: Profiles,
+    fallback: Fallback,
+    divergence: Divergence,
+    metrics: Metrics,
+}
+
+impl Bot {
+    pub fn new(game: &Game) -> Bot {
+        Bot {
+            total_halite: game.map.iter().map(|cell| cell.halite).sum(),
+            paths: HashMap::new(),
+            stats: Stats::new(),
+            collisions: Collisions::new(),
+            profiles: Profiles::new(),
+            fallback: Fallback::new(),
+            divergence: Divergence::new(),
+            metrics: Metrics::new(),
+        }
+    }
+
+    /// Paths ships are following into the next turn
+    pub fn paths(&self) -> &HashMap<ShipId, VecDeque<Action>> {
+        &self.paths
+    }
+
+    /// Play until the engine ends the game, handing each turn's commands to end_turn
+    pub fn run(&mut self, game: &mut Game, mut end_turn: impl FnMut(&Game, &[Command])) {
+        // Time spent waiting on the engine is not part of the turn
+        while game.wait_frame() {
+            self.stats.start();
+            {
+                let _span = self.stats.span("update_frame");
+                if !game.update_frame() {
+                    break;
+                }
+            }
+
+            let command_queue = self.turn(game);
+
+            self.stats.end(game.turn_number);
+            end_turn(game, &command_queue);
+        }
+    }
+
+    /// Commands for the frame the game has just read
+    pub fn turn(&mut self, game: &Game) -> Vec<Command> {
+        let stats = &self.stats;
+        let paths = &mut self.paths;
+
+        self.divergence.check(game);
+
+        let crashes = self.collisions.update(game);
+        self.metrics.update(game, &crashes);
+        self.profiles.update(game, &crashes);
+        self.fallback.update_frame(game);
+
+        let crashed = crashes.iter()
+            .filter(|crash| crash.ships.iter().any(|&(owner, _)| owner != game.my_id))
+            .map(|crash| crash.pos)
+            .collect();
+
+        let mut timeline = Timeline::from(game, crashed, paths, &self.profiles, self.collisions.spills(), stats);
+        let mut command_queue = Vec::new();
+        self.metrics.plan(timeline.poisoned());
+
+        let halite_remaining: usize = game.map.iter().map(|cell| cell.halite).sum();
+        let turn_limit = game.constants.max_turns * 3 / 4;
+        let early_game = halite_remaining > self.total_halite / 2 && game.turn_number < turn_limit;
+
+        if early_game {
+            timeline.make_dropoff(paths, stats);
+        }
+
+        let spawn = early_game && timeline.reserve_spawn();
+
+        command_queue.extend(timeline.path_ships(paths, &mut self.fallback, stats));
+
+        if spawn && timeline.spawn_ship() {
+            command_queue.push(Command::spawn_ship());
+        }
+
+        self.divergence.predict(game, &timeline.state(1), &command_queue);
+
+        command_queue
+    }
+
+    /// Log the game's timings and write its metrics report
+    pub fn finish(&self) {
+        self.stats.summary();
+        Log::report("metrics.json", &self.metrics.report(&self.stats));
+    }
+}
@@ -0,0 +1,219 @@
+use hlt::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Turns dropped halite is expected to stay before someone collects it
+pub const SPILL_LIFETIME: usize = 10;
+/// Extra weight given to dropped halite over regular halite
+pub const SPILL_PRIORITY: usize = 1;
+
+pub struct Collision {
+    pub turn: usize,
+    pub pos: Position,
+    pub ships: Vec<(PlayerId, ShipId)>,
+    pub dropped: usize,
+}
+
+#[derive(Copy, Clone)]
+pub struct Spill {
+    pub amount: usize,
+    pub expires: usize,
+}
+
+#[derive(Default)]
+pub struct Collisions {
+    ships_last: HashMap<ShipId, Ship>,
+    dropoffs_last: HashSet<DropoffId>,
+    halite_last: Vec<Vec<usize>>,
+    spills: HashMap<Position, Spill>,
+}
+
+impl Collisions {
+    pub fn new() -> Collisions {
+        Collisions::default()
+    }
+
+    /// Cells holding halite dropped by sunk ships
+    pub fn spills(&self) -> &HashMap<Position, Spill> {
+        &self.spills
+    }
+
+    /// Find collisions since the last frame
+    pub fn update(&mut self, game: &Game) -> Vec<Collision> {
+        let structures: HashMap<Position, PlayerId> = game.players.iter()
+            .map(|p| (p.shipyard.position, p.id))
+            .chain(game.dropoffs.values().map(|d| (d.position, d.owner)))
+            .collect();
+
+        // Ships which vanished without being converted into a dropoff
+        let vanished: Vec<&Ship> = self.ships_last.values()
+            .filter(|ship| !game.ships.contains_key(&ship.id))
+            .filter(|ship| !game.dropoffs.values().any(|d| {
+                d.owner == ship.owner && d.position == ship.position && !self.dropoffs_last.contains(&d.id)
+            }))
+            .collect();
+
+        let spike = |pos: Position| {
+            let before = self.halite_last.get(pos.y as usize).and_then(|row| row.get(pos.x as usize)).cloned().unwrap_or(0);
+            game.map.at_position(&pos).halite.saturating_sub(before)
+        };
+
+        // Every cell a vanished ship could have ended its turn on
+        let mut candidates: HashMap<Position, Vec<&Ship>> = HashMap::new();
+        for &ship in &vanished {
+            for pos in std::iter::once(ship.position).chain(ship.position.get_surrounding_cardinals()) {
+                candidates.entry(game.map.normalize(&pos)).or_default().push(ship);
+            }
+        }
+
+        // Prefer cells shared by the most ships, then cells where halite appeared
+        let mut cells: Vec<(Position, Vec<&Ship>)> = candidates.into_iter().collect();
+        cells.sort_by_key(|(pos, ships)| {
+            let structure = structures.contains_key(pos);
+            (std::cmp::Reverse(ships.len()), std::cmp::Reverse(structure), std::cmp::Reverse(spike(*pos)), pos.x, pos.y)
+        });
+
+        let mut collisions = Vec::new();
+        let mut assigned: Vec<ShipId> = Vec::new();
+        for (pos, ships) in cells {
+            let ships: Vec<&Ship> = ships.into_iter().filter(|s| !assigned.contains(&s.id)).collect();
+
+            // A lone ship can only have sunk against a ship spawned on a shipyard
+            let shipyard = game.players.iter().any(|p| p.shipyard.position == pos);
+            if ships.is_empty() || (ships.len() == 1 && !shipyard) {
+                continue;
+            }
+
+            assigned.extend(ships.iter().map(|s| s.id));
+
+            let dropped = if structures.contains_key(&pos) { 0 } else { spike(pos) };
+            let ships: Vec<(PlayerId, ShipId)> = ships.iter().map(|s| (s.owner, s.id)).collect();
+
+            collisions.push(Collision { turn: game.turn_number, pos, ships, dropped });
+        }
+
+        // Ships left over sank somewhere we cannot pin down, so assume where they were
+        for ship in vanished.iter().filter(|s| !assigned.contains(&s.id)) {
+            let pos = ship.position;
+            let dropped = if structures.contains_key(&pos) { 0 } else { spike(pos) };
+            collisions.push(Collision { turn: game.turn_number, pos, ships: vec![(ship.owner, ship.id)], dropped });
+        }
+
+        // Forget spills which expired or were collected
+        let turn = game.turn_number;
+        self.spills.retain(|pos, spill| spill.expires > turn && game.map.at_position(pos).halite * 2 >= spill.amount);
+
+        for collision in &collisions {
+            if collision.dropped > 0 {
+                let spill = Spill { amount: collision.dropped, expires: turn + SPILL_LIFETIME };
+                self.spills.insert(collision.pos, spill);
+            }
+
+            Log::log(Category::Navi, collision.pos, format_args!("_crash[{}:h{}]_", collision.ships.len(), collision.dropped), "red");
+        }
+
+        self.ships_last = game.ships.clone();
+        self.dropoffs_last = game.dropoffs.keys().cloned().collect();
+        self.halite_last = game.map.cells.iter().map(|row| row.iter().map(|cell| cell.halite).collect()).collect();
+
+        collisions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scenario::Scenario;
+    use fixture::Frames;
+
+    /// Frame of a scenario some turns on, with halite placed on cells
+    fn frame(map: &str, turns: usize, halite: &[(Position, usize)]) -> Game {
+        let mut game = Scenario::new(map).game();
+        game.turn_number += turns;
+        for &(pos, amount) in halite {
+            game.map.at_position_mut(&pos).halite = amount;
+        }
+        game
+    }
+
+    const EMPTY: &str = "
+        . . . . . .
+        . . . . . .
+        . S . . . S[1]
+    ";
+
+    #[test]
+    fn two_ships_crash_and_spill() {
+        let mut collisions = Collisions::new();
+        collisions.update(&frame("
+            . . . . . .
+            . a[300] . b[200] . .
+            . S . . . S[1]
+        ", 0, &[]));
+
+        // Both gone, with their cargo on the cell between them
+        let crash = Position { x: 2, y: 1 };
+        let found = collisions.update(&frame(EMPTY, 1, &[(crash, 500)]));
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pos, crash);
+        let mut ships = found[0].ships.clone();
+        ships.sort_by_key(|&(_, ship_id)| ship_id.0);
+        assert_eq!(ships, vec![(PlayerId(0), ShipId(0)), (PlayerId(1), ShipId(1))]);
+        assert_eq!(found[0].dropped, 500);
+        assert_eq!(collisions.spills()[&crash].amount, 500);
+    }
+
+    #[test]
+    fn crash_on_structure_drops_nothing() {
+        let mut collisions = Collisions::new();
+        collisions.update(&frame("
+            . . . . . .
+            . . . . . b[200]
+            . S . . a[300] S[1]
+        ", 0, &[]));
+
+        // Both moved onto the enemy shipyard, which banks the cargo
+        let found = collisions.update(&frame(EMPTY, 1, &[]));
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pos, Position { x: 5, y: 2 });
+        assert_eq!(found[0].ships.len(), 2);
+        assert_eq!(found[0].dropped, 0);
+        assert!(collisions.spills().is_empty());
+    }
+
+    #[test]
+    fn spills_expire() {
+        let crash = Position { x: 2, y: 1 };
+        let mut collisions = Collisions::new();
+        collisions.update(&frame("
+            . . . . . .
+            . a[300] . b[200] . .
+            . S . . . S[1]
+        ", 0, &[]));
+        collisions.update(&frame(EMPTY, 1, &[(crash, 500)]));
+
+        collisions.update(&frame(EMPTY, SPILL_LIFETIME, &[(crash, 500)]));
+        assert!(collisions.spills().contains_key(&crash));
+
+        collisions.update(&frame(EMPTY, SPILL_LIFETIME + 1, &[(crash, 500)]));
+        assert!(collisions.spills().is_empty());
+    }
+
+    #[test]
+    fn collected_spills_are_forgotten() {
+        let crash = Position { x: 2, y: 1 };
+        let mut collisions = Collisions::new();
+        collisions.update(&frame("
+            . . . . . .
+            . a[300] . b[200] . .
+            . S . . . S[1]
+        ", 0, &[]));
+        collisions.update(&frame(EMPTY, 1, &[(crash, 500)]));
+
+        // Mostly mined away
+        collisions.update(&frame(EMPTY, 2, &[(crash, 200)]));
+        assert!(collisions.spills().is_empty());
+    }
+}
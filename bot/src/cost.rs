@@ -4,7 +4,7 @@ use std::cmp::Ordering;
 #[derive(Eq, Clone, Copy)]
 pub struct Cost(pub usize, pub i32);
 
-const TIME_RATIO: i32 = 100;
+pub const TIME_RATIO: i32 = 100;
 
 impl<'a> From<&'a Cost> for i32 {
     fn from(cost: &Cost) -> i32 {
@@ -0,0 +1,250 @@
+use hlt::*;
+use state::State;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Mismatch {
+    /// Ship ended where predicted with different cargo
+    Mining,
+    /// Ship ended somewhere other than predicted
+    Blocked,
+    /// Ship predicted to exist was sunk
+    Lost,
+    /// Our banked halite differs
+    Bank,
+    /// Cell halite differs where none of our ships were
+    Cell,
+    /// Enemy ended somewhere other than forecast
+    Enemy,
+}
+
+impl Mismatch {
+    fn name(&self) -> &'static str {
+        match *self {
+            Mismatch::Mining => "mining",
+            Mismatch::Blocked => "blocked",
+            Mismatch::Lost => "lost",
+            Mismatch::Bank => "bank",
+            Mismatch::Cell => "cell",
+            Mismatch::Enemy => "enemy",
+        }
+    }
+}
+
+const ALL: [Mismatch; 6] = [
+    Mismatch::Mining,
+    Mismatch::Blocked,
+    Mismatch::Lost,
+    Mismatch::Bank,
+    Mismatch::Cell,
+    Mismatch::Enemy,
+];
+
+/// Compares the state predicted for this turn with the frame which arrived
+#[derive(Default)]
+pub struct Divergence {
+    predicted: Option<State>,
+    counts: HashMap<Mismatch, usize>,
+    /// Ship turns left unchecked because no state was planned for them
+    skipped: usize,
+}
+
+impl Divergence {
+    pub fn new() -> Divergence {
+        Divergence::default()
+    }
+
+    /// Running count of mismatches in a category
+    pub fn count(&self, mismatch: Mismatch) -> usize {
+        self.counts.get(&mismatch).cloned().unwrap_or(0)
+    }
+
+    /// Ship turns which could not be checked so far
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Remember the state predicted for next turn, given the commands sent
+    pub fn predict(&mut self, game: &Game, state: &State, commands: &[Command]) {
+        let mut state = state.clone();
+
+        // Ships moved by the fallback have no planned state, unlike those building a dropoff
+        self.skipped += game.ships.values()
+            .filter(|ship| ship.owner == game.my_id && !state.ships.contains_key(&ship.id))
+            .filter(|ship| !commands.contains(&Command::transform_ship_into_dropoff_site(ship.id)))
+            .count();
+
+        // Spawning is decided after planning, so its cost is not in the timeline
+        if commands.contains(&Command::spawn_ship()) {
+            state.halite = state.halite.saturating_sub(state.constants.ship_cost);
+        }
+
+        self.predicted = Some(state);
+    }
+
+    /// Log and count every way the new frame differs from the prediction
+    pub fn check(&mut self, game: &Game) -> Vec<(Mismatch, Position)> {
+        let predicted = match self.predicted.take() {
+            Some(state) => state,
+            None => return Vec::new(),
+        };
+
+        if predicted.turn != game.turn_number {
+            return Vec::new();
+        }
+
+        let mut mismatches = Vec::new();
+
+        for &(ship_id, (pos, halite)) in predicted.ships.iter() {
+            match game.ships.get(&ship_id) {
+                None => mismatches.push((Mismatch::Lost, pos)),
+                Some(ship) if ship.position != pos => mismatches.push((Mismatch::Blocked, ship.position)),
+                Some(ship) if ship.halite != halite => mismatches.push((Mismatch::Mining, pos)),
+                Some(_) => (),
+            }
+        }
+
+        // Cells under ships are either checked through the ship or mined by an enemy,
+        // which is not modelled
+        let occupied: HashSet<Position> = game.ships.values().map(|ship| ship.position).collect();
+
+        for (pos, &halite) in predicted.map.iter() {
+            let actual = game.map.at_position(&pos).halite;
+            if actual != halite && predicted.taken[pos].is_none() && !occupied.contains(&pos) {
+                mismatches.push((Mismatch::Cell, pos));
+            }
+        }
+
+        let me = &game.players[game.my_id.0];
+        if me.halite != predicted.halite {
+            mismatches.push((Mismatch::Bank, me.shipyard.position));
+        }
+
+        let enemies: HashMap<ShipId, Position> = game.ships.values()
+            .filter(|ship| ship.owner != game.my_id)
+            .map(|ship| (ship.id, ship.position))
+            .collect();
+
+        let t = predicted.turn - predicted.start;
+        for &(pos, enemy) in predicted.enemies.iter() {
+            if let Some(&actual) = enemies.get(&enemy.id) {
+                if actual != predicted.forecast_enemy(pos, t) {
+                    mismatches.push((Mismatch::Enemy, actual));
+                }
+            }
+        }
+
+        for &(mismatch, pos) in &mismatches {
+            *self.counts.entry(mismatch).or_insert(0) += 1;
+            Log::log(Category::Planner, pos, format_args!("_div:{}_", mismatch.name()), "orange");
+        }
+
+        if mismatches.iter().any(|&(mismatch, _)| mismatch != Mismatch::Enemy) {
+            let totals: Vec<String> = ALL.iter().map(|m| format!("{}:{}", m.name(), self.count(*m))).collect();
+            Log::warn(Category::Planner, format_args!("Divergence {} skipped:{}", totals.join(" "), self.skipped));
+        }
+
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scenario::Scenario;
+    use fixture::Frames;
+
+    const MAP: &str = "
+        . . . . . . .
+        . a . . 2 . .
+        . . . . . . .
+        . S . . b . .
+        . . . . . . S[1]
+    ";
+
+    /// State predicting the scenario's frame exactly, with our ships in it
+    fn predicted(game: &Game) -> State {
+        let mut state = Scenario::new(MAP).state();
+        for ship in game.ships.values().filter(|ship| ship.owner == game.my_id) {
+            state.add_ship(ship);
+        }
+        state
+    }
+
+    fn check(game: &Game, state: State) -> Vec<Mismatch> {
+        let mut divergence = Divergence::new();
+        divergence.predicted = Some(state);
+        divergence.check(game).into_iter().map(|(mismatch, _)| mismatch).collect()
+    }
+
+    #[test]
+    fn exact_prediction_matches() {
+        let game = Scenario::new(MAP).game();
+        assert_eq!(check(&game, predicted(&game)), vec![]);
+    }
+
+    #[test]
+    fn each_difference_is_categorised() {
+        let game = Scenario::new(MAP).game();
+        let ship = ShipId(0);
+        let (pos, halite) = predicted(&game).ship(ship);
+
+        let mut state = predicted(&game);
+        state.ships.insert(ship, (pos, halite + 10));
+        assert_eq!(check(&game, state), vec![Mismatch::Mining]);
+
+        let mut state = predicted(&game);
+        state.rm_ship(ship);
+        state.ships.insert(ship, (Position { x: 2, y: 1 }, halite));
+        state.taken.set(Position { x: 2, y: 1 }, Some(ship));
+        assert_eq!(check(&game, state), vec![Mismatch::Blocked]);
+
+        let mut state = predicted(&game);
+        state.ships.insert(ShipId(9), (Position { x: 0, y: 0 }, 0));
+        assert_eq!(check(&game, state), vec![Mismatch::Lost]);
+
+        let mut state = predicted(&game);
+        state.halite += 1;
+        assert_eq!(check(&game, state), vec![Mismatch::Bank]);
+
+        let mut state = predicted(&game);
+        state.update_hal(Position { x: 4, y: 1 }, 150);
+        assert_eq!(check(&game, state), vec![Mismatch::Cell]);
+
+        let mut state = predicted(&game);
+        let enemy = state.enemies.remove(&Position { x: 4, y: 3 }).unwrap();
+        state.enemies.insert(Position { x: 5, y: 3 }, enemy);
+        assert_eq!(check(&game, state), vec![Mismatch::Enemy]);
+
+        // Counts run across checks
+        let mut divergence = Divergence::new();
+        for _ in 0..2 {
+            let mut state = predicted(&game);
+            state.halite += 1;
+            divergence.predicted = Some(state);
+            divergence.check(&game);
+        }
+        assert_eq!(divergence.count(Mismatch::Bank), 2);
+        assert_eq!(divergence.count(Mismatch::Lost), 0);
+    }
+
+    #[test]
+    fn unplanned_ships_are_skipped() {
+        let game = Scenario::new(MAP).game();
+        let mut divergence = Divergence::new();
+
+        divergence.predict(&game, &predicted(&game), &[]);
+        assert_eq!(divergence.skipped(), 0);
+
+        // Moved by the fallback, so missing from the plan
+        let mut state = predicted(&game);
+        state.rm_ship(ShipId(0));
+        divergence.predict(&game, &state, &[Command::move_ship(ShipId(0), Direction::North)]);
+        assert_eq!(divergence.skipped(), 1);
+
+        // Building a dropoff, so expected to be missing
+        divergence.predict(&game, &state, &[Command::transform_ship_into_dropoff_site(ShipId(0))]);
+        assert_eq!(divergence.skipped(), 1);
+    }
+}
@@ -0,0 +1,189 @@
+use hlt::*;
+use state::{State, div_ceil};
+use cost::TIME_RATIO;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+/// Share of a neighbour's halite counted when moving there to mine next turn
+const MOVE_DISCOUNT: f32 = 0.5;
+/// Penalty for moving back onto the cell a ship just left
+const BACKTRACK_PENALTY: i32 = 50;
+/// Penalty for sitting on one of our dropoffs
+const BLOCKING_PENALTY: i32 = 200;
+/// Weight of the distance to target for an empty ship, rising to 1 for a full one
+const BASE_HOMING: f32 = 0.1;
+
+/// Scores every move for ships without a planned path
+#[derive(Default)]
+pub struct Fallback {
+    fields: HashMap<Position, Vec<i32>>,
+    current: HashMap<ShipId, Position>,
+    previous: HashMap<ShipId, Position>,
+}
+
+impl Fallback {
+    pub fn new() -> Fallback {
+        Fallback::default()
+    }
+
+    pub fn update_frame(&mut self, game: &Game) {
+        self.fields.clear();
+
+        let current = game.ships.values()
+            .filter(|ship| ship.owner == game.my_id)
+            .map(|ship| (ship.id, ship.position))
+            .collect();
+
+        self.previous = std::mem::replace(&mut self.current, current);
+    }
+
+    /// Cost of the cheapest route from every cell to target, counting turns and move costs
+    fn field(&mut self, state: &State, target: Position) -> &Vec<i32> {
+        self.fields.entry(target).or_insert_with(|| {
+            let index = |pos: Position| state.torus.index(pos);
+            let mut field = vec![i32::MAX; state.torus.size()];
+            let mut queue = BinaryHeap::new();
+
+            field[index(target)] = 0;
+            queue.push(Reverse((0, target.x, target.y)));
+
+            // Search outwards from the target, paying to leave each cell towards it
+            while let Some(Reverse((cost, x, y))) = queue.pop() {
+                let pos = Position { x, y };
+                if cost > field[index(pos)] {
+                    continue;
+                }
+
+                for neighbour in pos.get_surrounding_cardinals() {
+                    let neighbour = state.normalize(neighbour);
+                    let step = TIME_RATIO + (state.halite(neighbour) / state.constants.move_cost_ratio) as i32;
+
+                    if cost + step < field[index(neighbour)] {
+                        field[index(neighbour)] = cost + step;
+                        queue.push(Reverse((cost + step, neighbour.x, neighbour.y)));
+                    }
+                }
+            }
+
+            field
+        })
+    }
+
+    /// Directions for ship ranked best first, leaving out those Navi refuses
+    pub fn rank(&mut self, state: &State, nav: &Navi, ship_id: ShipId, pos: Position, hal: usize, target: Position) -> Vec<Direction> {
+        let previous = self.previous.get(&ship_id).cloned();
+        let field = self.field(state, target);
+        let at = |p: Position| field[state.torus.index(p)];
+
+        let max_halite = state.constants.max_halite;
+        let cap = max_halite - hal;
+        let move_cost = (state.halite(pos) / state.constants.move_cost_ratio) as i32;
+
+        // The fuller the ship, the more getting to target matters over mining
+        let homing = BASE_HOMING + (1.0 - BASE_HOMING) * hal as f32 / max_halite as f32;
+
+        let mut scored: Vec<(Direction, i32)> = Vec::new();
+        for dir in Direction::get_all_cardinals().into_iter().chain(std::iter::once(Direction::Still)) {
+            let new_pos = state.normalize(pos.directional_offset(dir));
+
+            let danger = match nav.danger(ship_id, &new_pos) {
+                Some(danger) => danger,
+                None if dir == Direction::Still => 0,
+                None => continue,
+            };
+
+            let mut score = -danger - (at(new_pos) as f32 * homing) as i32;
+
+            if dir == Direction::Still {
+                let mined = div_ceil(state.halite(pos), state.constants.extract_ratio).min(cap);
+                score += state.inspire(pos, mined) as i32;
+
+                if state.dropoffs.contains(&pos) {
+                    score -= BLOCKING_PENALTY;
+                }
+            } else {
+                let mined = div_ceil(state.halite(new_pos), state.constants.extract_ratio).min(cap);
+                score += (state.inspire(new_pos, mined) as f32 * MOVE_DISCOUNT * (1.0 - homing)) as i32;
+                score -= move_cost;
+
+                if previous == Some(new_pos) {
+                    score -= BACKTRACK_PENALTY;
+                }
+            }
+
+            scored.push((dir, score));
+        }
+
+        // Stable sort keeps cardinal order between equal scores
+        scored.sort_by_key(|&(_, score)| Reverse(score));
+        scored.into_iter().map(|(dir, _)| dir).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scenario::Scenario;
+    use fixture::Frames;
+    use profile::Profiles;
+
+    fn rank(map: &str, hal: usize, target: Position) -> Vec<Direction> {
+        let scenario = Scenario::new(map);
+        let game = scenario.game();
+        let mut nav = Navi::new(game.map.width, game.map.height);
+        nav.update_frame(&game);
+
+        let ship = &game.ships[&ShipId(0)];
+        Fallback::new().rank(&scenario.state(), &nav, ship.id, ship.position, hal, target)
+    }
+
+    #[test]
+    fn empty_ship_heads_for_target() {
+        let ranked = rank("
+            . . . . . .
+            . a . . . .
+            . . . . . S
+        ", 0, Position { x: 4, y: 1 });
+
+        assert_eq!(ranked[0], Direction::East);
+    }
+
+    #[test]
+    fn cargo_trades_mining_for_homing() {
+        // Ship sitting on 300 halite, which it mines when empty and leaves when nearly full
+        let game = |cargo: usize| {
+            let mut game = Scenario::new(&format!("
+                . . . . . .
+                . a[{}] . . . .
+                . . . . . S
+            ", cargo)).game();
+            game.map.at_position_mut(&Position { x: 1, y: 1 }).halite = 300;
+            game
+        };
+
+        for &(cargo, expected) in &[(0, Direction::Still), (900, Direction::East)] {
+            let game = game(cargo);
+            let mut nav = Navi::new(game.map.width, game.map.height);
+            nav.update_frame(&game);
+
+            let state = State::from(&game, &Profiles::new(), &HashMap::new());
+            let ship = &game.ships[&ShipId(0)];
+            let ranked = Fallback::new().rank(&state, &nav, ship.id, ship.position, cargo, Position { x: 4, y: 1 });
+            assert_eq!(ranked[0], expected, "cargo {}", cargo);
+        }
+    }
+
+    #[test]
+    fn refused_moves_are_left_out() {
+        let ranked = rank("
+            . . . . . .
+            . a b . . .
+            . . . . . S
+            . . . . . S[1]
+        ", 0, Position { x: 4, y: 1 });
+
+        assert!(!ranked.contains(&Direction::East));
+        assert!(ranked.contains(&Direction::Still));
+    }
+}
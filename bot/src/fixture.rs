@@ -0,0 +1,121 @@
+use hlt::*;
+use state::State;
+use profile::Profiles;
+use rng::Rng;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// Turn the synthetic frame is generated at
+const TURN: usize = 50;
+/// Distance from its shipyard within which a player's ships are placed
+const SPREAD: i32 = 10;
+
+/// Engine constants line with the standard game rules
+pub const CONSTANTS: &str = concat!(
+    "{\"NEW_ENTITY_ENERGY_COST\":1000,\"DROPOFF_COST\":4000,\"MAX_ENERGY\":1000,",
+    "\"MAX_TURNS\":500,\"EXTRACT_RATIO\":4,\"MOVE_COST_RATIO\":10,",
+    "\"INSPIRATION_ENABLED\":true,\"INSPIRATION_RADIUS\":4,\"INSPIRATION_SHIP_COUNT\":2,",
+    "\"INSPIRED_EXTRACT_RATIO\":4,\"INSPIRED_BONUS_MULTIPLIER\":2.0,\"INSPIRED_MOVE_COST_RATIO\":10}\n",
+);
+
+/// Source of engine frames that tests and benchmarks play a game from
+pub trait Frames {
+    /// Initial frame followed by a single turn update, in the engine's format
+    fn frames(&self) -> String;
+
+    /// Game parsed from the frames, as player 0 after the turn update
+    fn game(&self) -> Game {
+        let mut game = Game::from_reader(Cursor::new(self.frames()));
+        game.update_frame();
+        game
+    }
+
+    /// Planning state for the game, with no profiles or spills observed
+    fn state(&self) -> State {
+        State::from(&self.game(), &Profiles::new(), &HashMap::new())
+    }
+}
+
+/// Synthetic game of the given size, for benchmarks and tests
+pub struct Fixture {
+    pub width: usize,
+    pub height: usize,
+    pub num_players: usize,
+    pub ships_per_player: usize,
+    pub seed: u64,
+}
+
+impl Fixture {
+    pub fn new(width: usize, height: usize, num_players: usize, ships_per_player: usize) -> Fixture {
+        Fixture { width, height, num_players, ships_per_player, seed: 1 }
+    }
+
+    /// Shipyards spaced evenly around the middle of the map
+    fn shipyards(&self) -> Vec<Position> {
+        let (w, h) = (self.width as i32, self.height as i32);
+        let corners = [(w / 4, h / 4), (3 * w / 4, 3 * h / 4), (3 * w / 4, h / 4), (w / 4, 3 * h / 4)];
+
+        match self.num_players {
+            2 => vec![Position { x: w / 4, y: h / 2 }, Position { x: 3 * w / 4, y: h / 2 }],
+            n => corners.iter().take(n).map(|&(x, y)| Position { x, y }).collect(),
+        }
+    }
+}
+
+impl Frames for Fixture {
+    fn frames(&self) -> String {
+        let mut rng = Rng::new(self.seed);
+        let mut frames = String::new();
+
+        frames.push_str(CONSTANTS);
+
+        frames.push_str(&format!("{} 0\n", self.num_players));
+        let shipyards = self.shipyards();
+        for (id, pos) in shipyards.iter().enumerate() {
+            frames.push_str(&format!("{} {} {}\n", id, pos.x, pos.y));
+        }
+
+        // Mostly sparse halite with a few rich patches
+        frames.push_str(&format!("{} {}\n", self.width, self.height));
+        for y in 0..self.height {
+            let row: Vec<String> = (0..self.width)
+                .map(|x| {
+                    let halite = if rng.below(8) == 0 { 400 + rng.below(600) } else { rng.below(150) };
+                    let shipyard = shipyards.contains(&Position { x: x as i32, y: y as i32 });
+                    if shipyard { 0 } else { halite }
+                })
+                .map(|halite| halite.to_string())
+                .collect();
+            frames.push_str(&row.join(" "));
+            frames.push('\n');
+        }
+
+        frames.push_str(&format!("{}\n", TURN));
+
+        let torus = Torus::new(self.width, self.height);
+        let mut taken = Vec::new();
+        let mut ship_id = 0;
+        for (id, shipyard) in shipyards.iter().enumerate() {
+            frames.push_str(&format!("{} {} 0 {}\n", id, self.ships_per_player, 5000));
+
+            for _ in 0..self.ships_per_player {
+                let pos = loop {
+                    let dx = rng.below(2 * SPREAD as usize + 1) as i32 - SPREAD;
+                    let dy = rng.below(2 * SPREAD as usize + 1) as i32 - SPREAD;
+                    let pos = torus.normalize(Position { x: shipyard.x + dx, y: shipyard.y + dy });
+
+                    if !taken.contains(&pos) && !shipyards.contains(&pos) {
+                        break pos;
+                    }
+                };
+
+                taken.push(pos);
+                frames.push_str(&format!("{} {} {} {}\n", ship_id, pos.x, pos.y, rng.below(1000)));
+                ship_id += 1;
+            }
+        }
+
+        frames.push_str("0\n");
+        frames
+    }
+}
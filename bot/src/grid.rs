@@ -0,0 +1,122 @@
+use hlt::*;
+use std::ops::Index;
+use std::rc::Rc;
+
+/// Cells per copy-on-write chunk
+const CHUNK_SIZE: usize = 64;
+
+/// Persistent per-cell storage in the torus's row major order
+///
+/// Cloning only bumps a reference count. Writing copies the chunk holding the
+/// cell, and the list of chunks, if another clone still shares them.
+#[derive(Clone)]
+pub struct Grid<T: Clone> {
+    torus: Torus,
+    chunks: Rc<Vec<Rc<Vec<T>>>>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(torus: Torus, value: T) -> Grid<T> {
+        Grid::from_fn(torus, |_| value.clone())
+    }
+
+    pub fn from_fn(torus: Torus, f: impl FnMut(Position) -> T) -> Grid<T> {
+        let cells: Vec<T> = (0..torus.size()).map(|i| torus.position(i)).map(f).collect();
+
+        let chunks = cells.chunks(CHUNK_SIZE).map(|chunk| Rc::new(chunk.to_vec())).collect();
+        Grid { torus, chunks: Rc::new(chunks) }
+    }
+
+    #[inline]
+    pub fn get(&self, pos: Position) -> &T {
+        let i = self.torus.index(pos);
+        &self.chunks[i / CHUNK_SIZE][i % CHUNK_SIZE]
+    }
+
+    pub fn set(&mut self, pos: Position, value: T) {
+        let i = self.torus.index(pos);
+        let chunk = &mut Rc::make_mut(&mut self.chunks)[i / CHUNK_SIZE];
+        Rc::make_mut(chunk)[i % CHUNK_SIZE] = value;
+    }
+
+    /// Every cell with its position, row by row
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (Position, &'a T)> + 'a {
+        let torus = self.torus;
+        self.chunks.iter()
+            .flat_map(|chunk| chunk.iter())
+            .enumerate()
+            .map(move |(i, value)| (torus.position(i), value))
+    }
+}
+
+impl<T: Clone> Index<Position> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Position) -> &T {
+        self.get(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_leave_clones_alone() {
+        let mut grid = Grid::new(Torus::new(8, 8), 0);
+        let pos = Position { x: 3, y: 2 };
+        grid.set(pos, 1);
+
+        let original = grid.clone();
+        grid.set(pos, 2);
+        grid.set(Position { x: 7, y: 7 }, 3);
+
+        assert_eq!(original[pos], 1);
+        assert_eq!(original[Position { x: 7, y: 7 }], 0);
+        assert_eq!(grid[pos], 2);
+        assert_eq!(grid[Position { x: 7, y: 7 }], 3);
+    }
+
+    #[test]
+    fn writes_cross_chunk_boundaries() {
+        // 10x10 leaves the last chunk partly filled
+        let torus = Torus::new(10, 10);
+        let mut grid = Grid::new(torus, 0);
+        let shared = grid.clone();
+
+        for i in CHUNK_SIZE - 2..CHUNK_SIZE + 2 {
+            grid.set(torus.position(i), i);
+        }
+        grid.set(torus.position(torus.size() - 1), 99);
+
+        for i in 0..torus.size() {
+            let expected = match i {
+                _ if i == torus.size() - 1 => 99,
+                _ if (CHUNK_SIZE - 2..CHUNK_SIZE + 2).contains(&i) => i,
+                _ => 0,
+            };
+            assert_eq!(grid[torus.position(i)], expected, "cell {}", i);
+            assert_eq!(shared[torus.position(i)], 0);
+        }
+    }
+
+    #[test]
+    fn iter_walks_rows_of_a_non_square_torus() {
+        let torus = Torus::new(13, 7);
+        let grid = Grid::from_fn(torus, |pos| (pos.x, pos.y));
+
+        let cells: Vec<(Position, (i32, i32))> = grid.iter().map(|(pos, &value)| (pos, value)).collect();
+        assert_eq!(cells.len(), 13 * 7);
+        assert_eq!(cells[0].0, Position { x: 0, y: 0 });
+        assert_eq!(cells[12].0, Position { x: 12, y: 0 });
+        assert_eq!(cells[13].0, Position { x: 0, y: 1 });
+        assert_eq!(cells.last().unwrap().0, Position { x: 12, y: 6 });
+
+        for (pos, (x, y)) in cells {
+            assert_eq!((pos.x, pos.y), (x, y));
+        }
+
+        // Positions off the torus wrap onto it
+        assert_eq!(grid[Position { x: -1, y: 7 }], (12, 0));
+    }
+}
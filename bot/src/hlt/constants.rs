@@ -16,6 +16,8 @@ pub struct Constants {
     pub inspired_extract_ratio: usize,
     pub inspired_bonus_multiplier: f64,
     pub inspired_move_cost_ratio: usize,
+    /// Seed the engine generated the map from, or 0 if it was not sent
+    pub game_seed: u64,
 }
 
 impl Constants {
@@ -47,6 +49,7 @@ impl Constants {
             inspired_extract_ratio: Constants::get_value(&map, "INSPIRED_EXTRACT_RATIO"),
             inspired_bonus_multiplier: Constants::get_value(&map, "INSPIRED_BONUS_MULTIPLIER"),
             inspired_move_cost_ratio: Constants::get_value(&map, "INSPIRED_MOVE_COST_RATIO"),
+            game_seed: map.get("game_seed").and_then(|s| s.parse().ok()).unwrap_or(0),
         }
     }
 
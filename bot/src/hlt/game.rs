@@ -11,6 +11,7 @@ use hlt::PlayerId;
 use hlt::ship::Ship;
 use hlt::ShipId;
 use std::collections::HashMap;
+use std::io::BufRead;
 
 pub struct Game {
     pub constants: Constants,
@@ -23,17 +24,33 @@ pub struct Game {
     input: Input,
 }
 
+/// Reads the game from the engine on stdin, as `Game::new` does
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
 impl Game {
     pub fn new() -> Game {
-        let mut input = Input::new();
+        let game = Game::from_input(Input::new());
+        Log::open(game.my_id.0, game.constants.game_seed);
+
+        game
+    }
+
+    /// Game reading its initial frame and updates from reader, without opening a log file
+    pub fn from_reader(reader: impl BufRead + 'static) -> Game {
+        Game::from_input(Input::from_reader(reader))
+    }
+
+    fn from_input(mut input: Input) -> Game {
         let constants = Constants::new(&input.read_and_return_line());
 
         input.read_and_parse_line();
         let num_players = input.next_usize();
         let my_id = PlayerId(input.next_usize());
 
-        Log::open(my_id.0);
-
         let mut players: Vec<Player> = Vec::new();
         for _ in 0..num_players {
             players.push(Player::generate(&mut input));
@@ -57,10 +74,20 @@ impl Game {
         println!("{}", name);
     }
 
-    pub fn update_frame(&mut self) {
+    /// Wait for the engine to send the next frame, returning false once it has ended the game
+    pub fn wait_frame(&mut self) -> bool {
+        self.input.wait()
+    }
+
+    /// Read the next frame, or return false once the engine has ended the game
+    pub fn update_frame(&mut self) -> bool {
         let input = &mut self.input;
 
         input.read_and_parse_line();
+        if input.eof {
+            return false;
+        }
+
         self.turn_number = input.next_usize();
 
         self.ships.clear();
@@ -94,6 +121,8 @@ impl Game {
                 self.map.at_entity_mut(dropoff).structure = Structure::Dropoff(*dropoff_id);
             }
         }
+
+        true
     }
 
     pub fn end_turn(&self, commands: &[Command]) {
@@ -4,11 +4,12 @@ use hlt::map_cell::MapCell;
 use hlt::map_cell::Structure;
 use hlt::map_cell_iterator::MapCellIterator;
 use hlt::position::Position;
-use std::cmp::min;
+use hlt::torus::Torus;
 
 pub struct GameMap {
     pub width: usize,
     pub height: usize,
+    pub torus: Torus,
     pub cells: Vec<Vec<MapCell>>,
 }
 
@@ -39,24 +40,11 @@ impl GameMap {
     }
 
     pub fn calculate_distance(&self, source: &Position, target: &Position) -> usize {
-        let normalized_source = self.normalize(source);
-        let normalized_target = self.normalize(target);
-
-        let dx = (normalized_source.x - normalized_target.x).abs() as usize;
-        let dy = (normalized_source.y - normalized_target.y).abs() as usize;
-
-        let toroidal_dx = min(dx, self.width - dx);
-        let toroidal_dy = min(dy, self.height - dy);
-
-        return toroidal_dx + toroidal_dy;
+        self.torus.distance(*source, *target)
     }
 
     pub fn normalize(&self, position: &Position) -> Position {
-        let width = self.width as i32;
-        let height = self.height as i32;
-        let x = ((position.x % width) + width) % width;
-        let y = ((position.y % height) + height) % height;
-        Position { x, y }
+        self.torus.normalize(*position)
     }
 
     pub fn update(&mut self, input: &mut Input) {
@@ -94,6 +82,6 @@ impl GameMap {
             cells.push(row);
         }
 
-        GameMap { width, height, cells }
+        GameMap { width, height, torus: Torus::new(width, height), cells }
     }
 }
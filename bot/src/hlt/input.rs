@@ -1,26 +1,37 @@
 use hlt::log::Log;
-use std::io::stdin;
-use std::process::exit;
+use std::io::{stdin, BufRead, BufReader};
 use std::str::FromStr;
 
 pub struct Input {
+    reader: Box<dyn BufRead>,
     tokens: Vec<String>,
     current_token: usize,
+    /// The engine closed stdin, which it does when the game ends
+    pub eof: bool,
 }
 
 impl Input {
     pub fn new() -> Input {
-        Input { tokens: Vec::new(), current_token: 0 }
+        Input::from_reader(BufReader::new(stdin()))
+    }
+
+    /// Read engine messages from something other than stdin, such as a recorded frame
+    pub fn from_reader(reader: impl BufRead + 'static) -> Input {
+        Input { reader: Box::new(reader), tokens: Vec::new(), current_token: 0, eof: false }
+    }
+
+    /// Block until more input arrives, returning false once the engine has closed it
+    pub fn wait(&mut self) -> bool {
+        let more = self.reader.fill_buf().map(|buf| !buf.is_empty()).unwrap_or(false);
+        self.eof |= !more;
+        more
     }
 
     pub fn read_and_return_line(&mut self) -> String {
         let mut buf = String::new();
-        match stdin().read_line(&mut buf) {
+        match self.reader.read_line(&mut buf) {
+            Ok(0) | Err(_) => self.eof = true,
             Ok(_) => (),
-            Err(_) => {
-                Log::flush();
-                exit(0);
-            },
         }
         buf
     }
@@ -43,6 +54,10 @@ impl Input {
     }
 
     pub fn next<T: FromStr>(&mut self) -> T {
+        if self.current_token >= self.tokens.len() {
+            Log::panic("Error: input: ran out of tokens, the engine ended mid frame.");
+        }
+
         let token = &self.tokens[self.current_token];
         self.current_token += 1;
 
@@ -7,11 +7,143 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt;
 use std::io::BufWriter;
+use std::panic;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+use std::time::SystemTime;
 
 lazy_static! {
     static ref LOG: Mutex<Log> = Mutex::new(Log::new());
 }
 
+/// Environment variable holding the log filter, e.g. `info,navi=trace,timing=off`
+const FILTER_VAR: &str = "BOT_LOG";
+/// Filter used when the environment variable is unset
+const DEFAULT_FILTER: &str = "debug";
+/// Directory log files are written to
+const DIR_VAR: &str = "BOT_LOG_DIR";
+/// File name without extension, with `{id}`, `{seed}` and `{time}` substituted
+const NAME_VAR: &str = "BOT_LOG_NAME";
+const DEFAULT_NAME: &str = "bot-{id}";
+/// Size in bytes after which each log file stops growing
+const MAX_BYTES_VAR: &str = "BOT_LOG_MAX_BYTES";
+/// Compiles every log call away, for submission builds
+const DISABLED: bool = cfg!(feature = "no_log");
+/// Annotations held between flushes, past which they are dropped, so runs which never
+/// start a turn, like tests and benchmarks, don't grow the queue without bound
+const MAX_PENDING: usize = 100_000;
+
+/// Severity of a log entry, most severe first
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn name(&self) -> &'static str {
+        match *self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+
+    /// Most verbose level allowed by a filter word, `None` for `off`
+    fn parse(s: &str) -> Option<Option<Level>> {
+        match s {
+            "off" => Some(None),
+            "error" => Some(Some(Level::Error)),
+            "warn" => Some(Some(Level::Warn)),
+            "info" => Some(Some(Level::Info)),
+            "debug" => Some(Some(Level::Debug)),
+            "trace" => Some(Some(Level::Trace)),
+            _ => None,
+        }
+    }
+}
+
+/// Part of the bot a log entry comes from
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Category {
+    Planner,
+    Navi,
+    Dropoff,
+    Spawn,
+    Timing,
+    Protocol,
+}
+
+const CATEGORIES: [Category; 6] = [
+    Category::Planner,
+    Category::Navi,
+    Category::Dropoff,
+    Category::Spawn,
+    Category::Timing,
+    Category::Protocol,
+];
+
+impl Category {
+    fn name(&self) -> &'static str {
+        match *self {
+            Category::Planner => "planner",
+            Category::Navi => "navi",
+            Category::Dropoff => "dropoff",
+            Category::Spawn => "spawn",
+            Category::Timing => "timing",
+            Category::Protocol => "protocol",
+        }
+    }
+}
+
+/// Most verbose level logged for each category
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Filter {
+    levels: [Option<Level>; 6],
+}
+
+impl Filter {
+    /// Parse a comma separated list of a default level and `category=level` overrides,
+    /// ignoring words which are not understood
+    pub fn parse(spec: &str) -> Filter {
+        let mut filter = Filter { levels: [None; 6] };
+
+        for word in spec.split(',').map(|word| word.trim().to_lowercase()) {
+            let mut parts = word.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(level), None) => if let Some(level) = Level::parse(level) {
+                    filter.levels = [level; 6];
+                },
+                (Some(category), Some(level)) => {
+                    let category = CATEGORIES.iter().position(|c| c.name() == category);
+                    if let (Some(i), Some(level)) = (category, Level::parse(level)) {
+                        filter.levels[i] = level;
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        filter
+    }
+
+    pub fn allows(&self, level: Level, category: Category) -> bool {
+        self.levels[category as usize].is_some_and(|max| level <= max)
+    }
+
+    /// Whether nothing at all is logged
+    pub fn is_off(&self) -> bool {
+        self.levels.iter().all(|level| level.is_none())
+    }
+}
+
 pub struct Message {
     turn: usize,
     pos: Position,
@@ -24,94 +156,312 @@ impl Display for Message {
         write!(f, "{{ \"t\": {}, \"x\": {}, \"y\": {}", self.turn, self.pos.x, self.pos.y)?;
 
         if let Some(ref message) = self.msg {
-            write!(f, ", \"msg\": \"{}\"", message)?;
+            write!(f, ", \"msg\": \"{}\"", escape(message))?;
         }
 
         if let Some(ref color) = self.col {
-            write!(f, ", \"color\": \"{}\"", color)?;
+            write!(f, ", \"color\": \"{}\"", escape(color))?;
         }
 
-        write!(f, " }},")
+        write!(f, " }}")
+    }
+}
+
+/// Escape a string for use inside a JSON string literal
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
     }
+    escaped
 }
 
+/// Where log files are written and how large they may grow
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Destination {
+    pub dir: PathBuf,
+    pub name: String,
+    pub max_bytes: Option<usize>,
+}
+
+impl Destination {
+    pub fn from_env() -> Destination {
+        Destination {
+            dir: env::var(DIR_VAR).map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(".")),
+            name: env::var(NAME_VAR).unwrap_or_else(|_| DEFAULT_NAME.to_string()),
+            max_bytes: env::var(MAX_BYTES_VAR).ok().and_then(|bytes| bytes.trim().parse().ok()),
+        }
+    }
+
+    /// Path of one of a bot's log files, with the name template filled in
+    pub fn path(&self, bot_id: usize, seed: u64, time: u64, extension: &str) -> PathBuf {
+        let name = self.name
+            .replace("{id}", &bot_id.to_string())
+            .replace("{seed}", &seed.to_string())
+            .replace("{time}", &time.to_string());
+
+        self.dir.join(format!("{}.{}", name, extension))
+    }
+}
+
+/// Log file which stops growing once it reaches the size cap
+struct Sink {
+    writer: BufWriter<File>,
+    bytes: usize,
+    max_bytes: Option<usize>,
+    full: bool,
+}
+
+impl Sink {
+    fn create(path: &PathBuf, max_bytes: Option<usize>) -> Option<Sink> {
+        match File::create(path) {
+            Ok(file) => Some(Sink { writer: BufWriter::new(file), bytes: 0, max_bytes, full: false }),
+            Err(e) => {
+                eprintln!("Couldn't open {} for logging, continuing without it: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Write s unless it would take the file past its cap, returning whether it was written
+    fn write(&mut self, s: &str) -> bool {
+        if self.full || self.max_bytes.is_some_and(|max| self.bytes + s.len() > max) {
+            self.full = true;
+            return false;
+        }
+
+        self.bytes += s.len();
+        self.writer.write_all(s.as_bytes()).is_ok()
+    }
+
+    /// Write s regardless of the cap, for closing out the file
+    fn force(&mut self, s: &str) {
+        self.bytes += s.len();
+        let _ = self.writer.write_all(s.as_bytes());
+    }
+
+    fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Fluorine overlay annotations go to `bot-N.log` as JSON and diagnostics go to
+/// `bot-N.txt` as plain text, each only when the filter allows them
 pub struct Log {
     turn: usize,
+    filter: Filter,
+    destination: Destination,
+    /// Bot id, game seed and time the log was opened with, which name its files
+    opened: Option<(usize, u64, u64)>,
     messages: Vec<Message>,
-    writer: Option<BufWriter<File>>
+    written: usize,
+    writer: Option<Sink>,
+    text: Option<Sink>,
+}
+
+impl Default for Log {
+    fn default() -> Log {
+        Log::new()
+    }
 }
 
 impl Log {
     pub fn new() -> Log {
-        let ret = Log {
+        let spec = env::var(FILTER_VAR).unwrap_or_else(|_| DEFAULT_FILTER.to_string());
+
+        Log {
             turn: 0,
+            filter: Filter::parse(&spec),
+            destination: Destination::from_env(),
+            opened: None,
             messages: Vec::new(),
-            writer: None 
-        };
-        ret
+            written: 0,
+            writer: None,
+            text: None,
+        }
     }
 
-    pub fn open(bot_id: usize) {
+    /// Create the log files, or carry on without them if they cannot be created
+    pub fn open(bot_id: usize, seed: u64) {
         let mut log = LOG.lock().unwrap();
 
-        if log.writer.is_some() {
+        if log.opened.is_some() {
             drop(log);
             Log::panic(format!("Error: log: tried to open({}) but we have already opened before.", bot_id));
         }
 
-        let filename = format!("bot-{}.log", bot_id);
-        let file = File::create(&filename).expect(&format!("Couldn't open file {} for logging!", &filename));
-        let mut writer = BufWriter::new(file);
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        log.opened = Some((bot_id, seed, time));
+
+        if DISABLED || log.filter.is_off() {
+            return;
+        }
+
+        let destination = log.destination.clone();
+
+        if let Err(e) = fs::create_dir_all(&destination.dir) {
+            eprintln!("Couldn't create {} for logging, continuing without it: {}", destination.dir.display(), e);
+            return;
+        }
+
+        log.writer = Sink::create(&destination.path(bot_id, seed, time, "log"), destination.max_bytes);
+        log.text = Sink::create(&destination.path(bot_id, seed, time, "txt"), destination.max_bytes);
+
+        if let Some(writer) = log.writer.as_mut() {
+            writer.force("[");
+        }
+
+        drop(log);
+
+        // Close the array even if the bot panics
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if let Ok(mut log) = LOG.try_lock() {
+                log.finish();
+            }
+            default_hook(info);
+        }));
+    }
+
+    /// Write a file named like the log files, even when logging is filtered out
+    pub fn report(extension: &str, contents: &str) {
+        let log = LOG.lock().unwrap();
+        let (bot_id, seed, time) = match log.opened {
+            Some(opened) => opened,
+            None => return,
+        };
+
+        let path = log.destination.path(bot_id, seed, time, extension);
+        if let Err(e) = fs::create_dir_all(&log.destination.dir).and_then(|_| fs::write(&path, contents)) {
+            eprintln!("Couldn't write {}: {}", path.display(), e);
+        }
+    }
 
-        writeln!(writer, "[").unwrap();
+    /// Replace the destination read from the environment, before the log is opened
+    pub fn set_destination(destination: Destination) {
+        LOG.lock().unwrap().destination = destination;
+    }
 
-        log.writer = Some(writer);
+    /// Replace the filter read from the environment
+    pub fn set_filter(filter: Filter) {
+        LOG.lock().unwrap().filter = filter;
+    }
 
+    /// Whether an entry would be logged, to skip building expensive messages
+    pub fn enabled(level: Level, category: Category) -> bool {
+        !DISABLED && LOG.lock().unwrap().filter.allows(level, category)
     }
 
     pub fn turn(turn_num: usize) {
         let mut log = LOG.lock().unwrap();
         log.turn = turn_num;
 
-        let messages: Vec<_> = log.messages.drain(..).collect();
+        log.write_messages();
+    }
+
+    /// Hold an annotation until the next flush, unless it could never be written
+    fn queue(&mut self, message: Message) {
+        let no_file = self.opened.is_some() && self.writer.is_none();
+        if !no_file && self.messages.len() < MAX_PENDING {
+            self.messages.push(message);
+        }
+    }
 
-        let writer = log.writer.as_mut().expect("Must open file before logging.");
+    fn write_messages(&mut self) {
+        let messages: Vec<_> = self.messages.drain(..).collect();
 
-        for message in messages {
-            writeln!(writer, "{}", message).unwrap();
+        // Messages are dropped when no log file was opened, as when benchmarking
+        if let Some(writer) = self.writer.as_mut() {
+            for message in messages {
+                let separator = if self.written == 0 { "\n" } else { ",\n" };
+                if !writer.write(&format!("{}{}", separator, message)) {
+                    break;
+                }
+
+                self.written += 1;
+            }
+
+            writer.flush();
         }
 
-        writer.flush().unwrap();
+        if let Some(text) = self.text.as_mut() {
+            text.flush();
+        }
     }
 
-    pub fn log(pos: Position, message: impl Into<String>, color: impl Into<String>) {
-        let mut log = LOG.lock().unwrap();
+    /// Write pending messages and terminate the array, after which nothing more is logged
+    fn finish(&mut self) {
+        self.write_messages();
 
-        let turn = log.turn;
-        let msg = Some(message.into());
-        let col = Some(color.into());
+        let mut full = false;
 
-        log.messages.push(Message { turn, pos, msg, col });
-    }
+        if let Some(mut writer) = self.writer.take() {
+            full |= writer.full;
+            writer.force("\n]\n");
+            writer.flush();
+        }
 
-    pub fn msg(pos: Position, message: impl Into<String>) {
-        let mut log = LOG.lock().unwrap();
+        if let Some(mut text) = self.text.take() {
+            full |= text.full;
+            if full {
+                text.force("Log size cap reached, later entries were dropped\n");
+            }
+            text.flush();
+        }
+    }
 
-        let turn = log.turn;
-        let msg = Some(message.into());
-        let col = None;
+    pub fn close() {
+        LOG.lock().unwrap().finish();
+    }
 
-        log.messages.push(Message { turn, pos, msg, col });
+    /// Combine logs from several bots into one array, accepting logs which were never terminated
+    pub fn merge(logs: &[String]) -> String {
+        let entries: Vec<&str> = logs.iter()
+            .map(|log| {
+                let log = log.trim();
+                let log = log.strip_prefix('[').unwrap_or(log).trim_start();
+                let log = log.strip_suffix(']').unwrap_or(log).trim_end();
+                log.strip_suffix(',').unwrap_or(log).trim()
+            })
+            .filter(|entries| !entries.is_empty())
+            .collect();
+
+        format!("[\n{}\n]\n", entries.join(",\n"))
     }
 
-    pub fn color(pos: Position, color: impl Into<String>) {
+    /// Queue an overlay annotation, which are all debug level
+    fn annotate(category: Category, pos: Position, msg: Option<&dyn Display>, col: Option<&dyn Display>) {
+        if DISABLED {
+            return;
+        }
+
         let mut log = LOG.lock().unwrap();
+        if log.filter.allows(Level::Debug, category) {
+            let turn = log.turn;
+            let msg = msg.map(|msg| msg.to_string());
+            let col = col.map(|col| col.to_string());
+            log.queue(Message { turn, pos, msg, col });
+        }
+    }
 
-        let turn = log.turn;
-        let msg = None;
-        let col = Some(color.into());
+    pub fn log(category: Category, pos: Position, message: impl Display, color: impl Display) {
+        Log::annotate(category, pos, Some(&message), Some(&color));
+    }
 
-        log.messages.push(Message { turn, pos, msg, col });
+    pub fn msg(category: Category, pos: Position, message: impl Display) {
+        Log::annotate(category, pos, Some(&message), None);
+    }
+
+    pub fn color(category: Category, pos: Position, color: impl Display) {
+        Log::annotate(category, pos, None, Some(&color));
     }
 
     pub fn flush() {
@@ -122,31 +472,141 @@ impl Log {
         Log::turn(turn_num);
     }
 
-    pub fn flash(message: impl Into<String>, color: impl Into<String>, level: i32) {
-        let log = LOG.lock().unwrap();
-        let turn_num = log.turn;
-        drop(log);
+    /// Write a line to the diagnostic stream, formatting the message only if it is kept
+    pub fn text(level: Level, category: Category, message: impl Display) {
+        if DISABLED {
+            return;
+        }
 
-        let zero_pos = Position { x: 0, y: level };
-        Log::log(zero_pos, message, color);
-        Log::turn(turn_num);
+        let mut log = LOG.lock().unwrap();
+        if !log.filter.allows(level, category) {
+            return;
+        }
+
+        let turn = log.turn;
+        if let Some(text) = log.text.as_mut() {
+            text.write(&format!("{:>3} {:<5} {:<8} {}\n", turn, level.name(), category.name(), message));
+        }
     }
 
-    pub fn info(message: impl Into<String>) {
-        Log::flash(message, "#9bc6ff", 0);
+    pub fn trace(category: Category, message: impl Display) {
+        Log::text(Level::Trace, category, message);
     }
 
-    pub fn warn(message: impl Into<String>) {
-        Log::flash(message, "#ffa500", 1);
+    pub fn debug(category: Category, message: impl Display) {
+        Log::text(Level::Debug, category, message);
     }
 
-    pub fn error(message: impl Into<String>) {
-        Log::flash(message, "#FF0000", 2);
+    pub fn info(category: Category, message: impl Display) {
+        Log::text(Level::Info, category, message);
     }
 
-    pub fn panic(message: impl Into<String>) -> ! {
-        Log::error(message);
+    pub fn warn(category: Category, message: impl Display) {
+        Log::text(Level::Warn, category, message);
+    }
+
+    pub fn error(category: Category, message: impl Display) {
+        Log::text(Level::Error, category, message);
+    }
+
+    pub fn panic(message: impl Display) -> ! {
+        Log::error(Category::Protocol, &message);
+        Log::close();
 
         exit(1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_escaped() {
+        let message = Message {
+            turn: 3,
+            pos: Position { x: 1, y: 2 },
+            msg: Some("say \"hi\"\\\n\u{1}".to_string()),
+            col: None,
+        };
+
+        assert_eq!(message.to_string(), "{ \"t\": 3, \"x\": 1, \"y\": 2, \"msg\": \"say \\\"hi\\\"\\\\\\n\\u0001\" }");
+    }
+
+    #[test]
+    fn merge_accepts_old_and_new_logs() {
+        let old = "[\n{ \"t\": 0, \"x\": 0, \"y\": 0 },\n{ \"t\": 1, \"x\": 0, \"y\": 0 },\n".to_string();
+        let new = "[\n{ \"t\": 0, \"x\": 1, \"y\": 1 }\n]\n".to_string();
+        let empty = "[\n]\n".to_string();
+
+        assert_eq!(Log::merge(&[old, empty, new]),
+                   "[\n{ \"t\": 0, \"x\": 0, \"y\": 0 },\n{ \"t\": 1, \"x\": 0, \"y\": 0 },\n{ \"t\": 0, \"x\": 1, \"y\": 1 }\n]\n");
+    }
+
+    #[test]
+    fn filter_applies_default_then_overrides() {
+        let filter = Filter::parse("info, navi=trace,timing=off,bogus=debug,loud");
+
+        assert!(filter.allows(Level::Info, Category::Planner));
+        assert!(!filter.allows(Level::Debug, Category::Planner));
+        assert!(filter.allows(Level::Trace, Category::Navi));
+        assert!(!filter.allows(Level::Error, Category::Timing));
+        assert!(!filter.is_off());
+    }
+
+    #[test]
+    fn filter_off_logs_nothing() {
+        let filter = Filter::parse("off");
+
+        assert!(filter.is_off());
+        for &category in &CATEGORIES {
+            assert!(!filter.allows(Level::Error, category));
+        }
+
+        assert!(!Filter::parse("warn,navi=off").is_off());
+    }
+
+    #[test]
+    fn destination_fills_in_template() {
+        let destination = Destination {
+            dir: PathBuf::from("logs"),
+            name: "game-{seed}-{time}-bot-{id}".to_string(),
+            max_bytes: None,
+        };
+
+        assert_eq!(destination.path(1, 42, 1500, "log"), PathBuf::from("logs/game-42-1500-bot-1.log"));
+    }
+
+    #[test]
+    fn pending_messages_are_bounded() {
+        let message = || Message { turn: 0, pos: Position { x: 0, y: 0 }, msg: None, col: None };
+
+        // Never opened, as in tests, so held until the cap
+        let mut log = Log::new();
+        for _ in 0..MAX_PENDING + 10 {
+            log.queue(message());
+        }
+        assert_eq!(log.messages.len(), MAX_PENDING);
+
+        // Opened without a file, so nothing is held
+        let mut log = Log::new();
+        log.opened = Some((0, 0, 0));
+        log.queue(message());
+        assert!(log.messages.is_empty());
+    }
+
+    #[test]
+    fn sink_stops_at_cap() {
+        let path = env::temp_dir().join(format!("my_bot-sink-{}.txt", std::process::id()));
+        let mut sink = Sink::create(&path, Some(10)).unwrap();
+
+        assert!(sink.write("12345"));
+        assert!(!sink.write("123456"));
+        assert!(!sink.write("1"));
+        sink.force("!");
+        sink.flush();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "12345!");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -43,6 +43,12 @@ pub use self::ship::*;
 #[allow(dead_code)]
 pub mod shipyard;
 pub use self::shipyard::*;
+#[allow(dead_code)]
+pub mod torus;
+pub use self::torus::*;
+#[allow(dead_code)]
+pub mod traffic;
+pub use self::traffic::*;
 
 #[allow(dead_code)]
 mod input;
@@ -3,17 +3,77 @@ use hlt::position::Position;
 use hlt::ship::Ship;
 use hlt::ShipId;
 use hlt::game::Game;
-use hlt::log::Log;
+use hlt::log::{Log, Category};
+use hlt::torus::Torus;
+use hlt::traffic::{Traffic, MAX_ARRIVALS};
 use std::collections::HashMap;
 use std::collections::HashSet;
+use pathfinding::kuhn_munkres::kuhn_munkres;
+use pathfinding::matrix::Matrix;
+
+const PLANNED_PRIORITY: i32 = 1000;
+const UNPLANNED_PRIORITY: i32 = 500;
+const RANK_PENALTY: i32 = 100;
+const RESERVED_PENALTY: i32 = 2 * PLANNED_PRIORITY;
+const INFEASIBLE: i32 = -1_000_000;
+
+/// Column a ship can be matched to, with the move there, its weight and the dropoff it arrives at if any
+type Edge = (usize, Direction, i32, Option<Position>);
+
+#[derive(Copy, Clone)]
+pub struct Avoidance {
+    /// Refuse to move onto cells held by enemy ships
+    pub block_occupied: bool,
+    /// Penalty per halite of cargo for ending the turn next to an enemy
+    pub adjacent_penalty: f32,
+    /// Cargo above which cells next to enemies are refused outright
+    pub block_adjacent_above: Option<usize>,
+}
+
+impl Default for Avoidance {
+    fn default() -> Avoidance {
+        Avoidance {
+            block_occupied: true,
+            adjacent_penalty: 0.5,
+            block_adjacent_above: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Resolver {
+    /// Resolve ships one at a time, asking blockers to move out of the way
+    #[default]
+    Signal,
+    /// Assign the whole fleet at once as a weighted bipartite matching
+    Matching,
+}
+
+impl Resolver {
+    /// Resolver named by `spec`, ignoring case, or None if it names none
+    pub fn parse(spec: &str) -> Option<Resolver> {
+        match spec.trim().to_lowercase().as_str() {
+            "signal" => Some(Resolver::Signal),
+            "matching" => Some(Resolver::Matching),
+            _ => None,
+        }
+    }
+}
 
 pub struct Navi {
     pub width: usize,
     pub height: usize,
+    pub torus: Torus,
     pub moving: HashMap<ShipId, (Position, Vec<Direction>)>,
+    pub planned: HashSet<ShipId>,
     pub occupied: Vec<Vec<Option<ShipId>>>,
+    pub enemies: Vec<Vec<bool>>,
+    pub cargo: HashMap<ShipId, usize>,
+    pub avoidance: Avoidance,
     pub terminal: bool,
     pub dropoffs: HashSet<Position>,
+    pub traffic: Traffic,
+    pub resolver: Resolver,
 }
 
 impl Navi {
@@ -26,10 +86,17 @@ impl Navi {
         Navi {
             width,
             height,
+            torus: Torus::new(width, height),
             moving: HashMap::new(),
+            planned: HashSet::new(),
             occupied,
+            enemies: vec![vec![false; width]; height],
+            cargo: HashMap::new(),
+            avoidance: Avoidance::default(),
             terminal: false,
             dropoffs: HashSet::new(),
+            traffic: Traffic::new(width, height),
+            resolver: Resolver::default(),
         }
     }
 
@@ -43,6 +110,7 @@ impl Navi {
 
     pub fn update_frame(&mut self, game: &Game) {
         self.clear();
+        self.traffic.update_frame(game);
 
         for player in &game.players {
             if player.id == game.my_id {
@@ -52,11 +120,12 @@ impl Navi {
             }
 
             for ship_id in &player.ship_ids {
+                let ship = &game.ships[ship_id];
                 if player.id == game.my_id {
-                    let ship = &game.ships[ship_id];
-                    if player.id == game.my_id || !self.dropoffs.contains(&ship.position) {
-                        self.mark_unsafe_ship(&ship);
-                    }
+                    self.mark_unsafe_ship(&ship);
+                    self.cargo.insert(ship.id, ship.halite);
+                } else {
+                    self.mark_enemy(&ship.position);
                 }
             }
         }
@@ -64,9 +133,12 @@ impl Navi {
 
     pub fn clear(&mut self) {
         self.moving.clear();
+        self.planned.clear();
+        self.cargo.clear();
         for y in 0..self.height {
             for x in 0..self.width {
                 self.occupied[y][x] = None;
+                self.enemies[y][x] = false;
             }
         }
     }
@@ -93,105 +165,126 @@ impl Navi {
         self.occupied[position.y as usize][position.x as usize] = None;
     }
 
-    pub fn mark_unsafe_ship(&mut self, ship: &Ship) {
-        self.mark_unsafe(&ship.position, ship.id);
+    /// Whether lanes and reservations let a ship move between two cells, ignored in terminal mode
+    pub fn lane_allows(&self, from: &Position, to: &Position) -> bool {
+        self.terminal || self.traffic.allows(from, to)
+    }
+
+    pub fn mark_enemy(&mut self, position: &Position) {
+        let position = self.normalize(position);
+        self.enemies[position.y as usize][position.x as usize] = true;
     }
 
-    pub fn get_unsafe_moves(&self, source: &Position, destination: &Position) -> Vec<Direction> {
-        let normalized_source = self.normalize(source);
-        let normalized_destination = self.normalize(destination);
+    pub fn is_enemy(&self, position: &Position) -> bool {
+        let position = self.normalize(position);
+        self.enemies[position.y as usize][position.x as usize]
+    }
 
-        let dx = (normalized_source.x - normalized_destination.x).abs() as usize;
-        let dy = (normalized_source.y - normalized_destination.y).abs() as usize;
+    /// Penalty for ship ending its turn at position, or None if it must not go there
+    pub fn danger(&self, ship_id: ShipId, position: &Position) -> Option<i32> {
+        let position = self.normalize(position);
 
-        let wrapped_dx = self.width - dx;
-        let wrapped_dy = self.height - dy;
+        // Our own dropoffs are safe to enter whatever is around them
+        if self.dropoffs.contains(&position) {
+            return Some(0);
+        }
 
-        let mut possible_moves: Vec<Direction> = Vec::new();
+        if self.avoidance.block_occupied && self.is_enemy(&position) {
+            return None;
+        }
 
-        if normalized_source.x < normalized_destination.x {
-            possible_moves.push(if dx > wrapped_dx { Direction::West } else { Direction::East });
-        } else if normalized_source.x > normalized_destination.x {
-            possible_moves.push(if dx < wrapped_dx { Direction::West } else { Direction::East });
+        let exposed = self.is_enemy(&position) || position.get_surrounding_cardinals().iter().any(|p| self.is_enemy(p));
+        if !exposed {
+            return Some(0);
         }
 
-        if normalized_source.y < normalized_destination.y {
-            possible_moves.push(if dy > wrapped_dy { Direction::North } else { Direction::South });
-        } else if normalized_source.y > normalized_destination.y {
-            possible_moves.push(if dy < wrapped_dy { Direction::North } else { Direction::South });
+        let cargo = self.cargo.get(&ship_id).cloned().unwrap_or(0);
+        if self.avoidance.block_adjacent_above.map(|limit| cargo > limit).unwrap_or(false) {
+            return None;
         }
 
-        possible_moves
+        Some((cargo as f32 * self.avoidance.adjacent_penalty) as i32)
     }
 
-    pub fn naive_navigate(&mut self, ship_id: ShipId, ship_pos: Position, dest: Position) {
-        // get_unsafe_moves normalizes for us
-        let mut directions = self.get_unsafe_moves(&ship_pos, &dest);
-
-        self.moving.insert(ship_id, (ship_pos, directions));
+    pub fn mark_unsafe_ship(&mut self, ship: &Ship) {
+        self.mark_unsafe(&ship.position, ship.id);
     }
 
     pub fn nav(&mut self, ship_id: ShipId, ship_pos: Position, dir: Direction) {
         self.moving.insert(ship_id, (ship_pos, vec![dir]));
+        self.planned.insert(ship_id);
     }
 
-    pub fn move_away(&mut self, ship_id: ShipId, ship_pos: Position) {
-        self.moving.insert(ship_id, (ship_pos, Direction::get_all_cardinals()));
+    /// Navigate with directions already ranked best first, staying included
+    pub fn ranked(&mut self, ship_id: ShipId, ship_pos: Position, directions: Vec<Direction>) {
+        self.moving.insert(ship_id, (ship_pos, directions));
     }
 
     pub fn move_ship(&mut self, ship_id: ShipId, old: Position, new: Position) {
         self.mark_safe(&old);
         self.mark_unsafe(&new, ship_id);
-    }
-
-    pub fn swap_ships(
-        &mut self,
-        (pos1, ship1): (Position, ShipId),
-        (pos2, ship2): (Position, ShipId),
-    ) {
-        self.mark_unsafe(&pos1, ship2);
-        self.mark_unsafe(&pos2, ship1);
+        self.traffic.record_move(&old, &new);
     }
 
     pub fn signal_move(
         &mut self,
         ship_id: ShipId,
         moves: &mut Vec<(ShipId, Direction)>,
-        signals: &mut HashMap<Position, HashSet<ShipId>>,
+        waiting: &mut Vec<(ShipId, Position, Direction)>,
     ) {
         let yellow = "#e2f442";
 
         // If we want to move
         if let Some((position, directions)) = self.moving.remove(&ship_id) {
+            // Best ranked directions first, with danger as a penalty on top of the rank as in matching,
+            // leaving out those we must not take
+            let mut directions: Vec<(Direction, i32)> = directions.into_iter()
+                .enumerate()
+                .filter_map(|(rank, dir)| {
+                    self.danger(ship_id, &position.directional_offset(dir)).map(|danger| (dir, rank as i32 * RANK_PENALTY + danger))
+                })
+                .collect();
+            directions.sort_by_key(|&(_, penalty)| penalty);
+
             // For each potential movement direction
-            for dir in directions {
+            for (dir, _) in directions {
                 let new_pos = self.normalize(&position.directional_offset(dir));
 
+                if dir != Direction::Still && !self.lane_allows(&position, &new_pos) {
+                    continue;
+                }
+
                 // Ship at target position
                 if let Some(unsafe_ship) = self.get(&new_pos) {
-                    // Ship wants to swap if they signal for my position
-                    if signals.get_mut(&position).map(|ships| ships.remove(&unsafe_ship)).unwrap_or_default() {
-                        self.swap_ships((position, ship_id), (new_pos, unsafe_ship));
-                        Log::log(position, "_swap1_", yellow);
-                        moves.push((ship_id, dir));
-                        moves.push((unsafe_ship, dir.invert_direction()));
+                    // Staying put, nothing to resolve
+                    if unsafe_ship == ship_id {
                         return
                     }
 
-                    // Ship doesn't want to swap, so signal them to move or swap
-                    signals.entry(new_pos) 
-                        .and_modify(|ships| {
-                            ships.insert(ship_id); 
-                        }).or_insert_with(|| {
-                            let mut ships = HashSet::new();
-                            ships.insert(ship_id);
-                            ships
-                        });
-                    self.signal_move(unsafe_ship, moves, signals);
-
-                    // If we swapped, return
-                    if self.get(&new_pos) == Some(ship_id) {
-                        Log::log(position, "_swap2_", yellow);
+                    // Ship is waiting on a chain of moves ending with us, so rotate the whole cycle
+                    if let Some(k) = waiting.iter().position(|&(id, _, _)| id == unsafe_ship) {
+                        let cycle: Vec<_> = waiting[k..].iter()
+                            .cloned()
+                            .chain(std::iter::once((ship_id, position, dir)))
+                            .collect();
+
+                        for &(id, pos, d) in &cycle {
+                            self.mark_unsafe(&pos.directional_offset(d), id);
+                            self.traffic.record_move(&pos, &pos.directional_offset(d));
+                            moves.push((id, d));
+                        }
+
+                        Log::log(Category::Navi, position, format_args!("_rot{}_", cycle.len()), yellow);
+                        return
+                    }
+
+                    // Ask the ship to move out of the way first
+                    waiting.push((ship_id, position, dir));
+                    self.signal_move(unsafe_ship, moves, waiting);
+                    waiting.pop();
+
+                    // If we were part of a rotation, return
+                    if moves.iter().any(|&(id, _)| id == ship_id) {
                         return
                     }
                 }
@@ -206,13 +299,20 @@ impl Navi {
         }
     }
 
-
     pub fn collect_moves(&mut self) -> Vec<(ShipId, Direction)> {
+        match self.resolver {
+            Resolver::Signal => self.collect_signalled(),
+            Resolver::Matching => self.collect_matched(),
+        }
+    }
+
+    pub fn collect_signalled(&mut self) -> Vec<(ShipId, Direction)> {
         let mut moves: Vec<(ShipId, Direction)> = Vec::new();
-        let ships: Vec<ShipId> = self.moving.keys().cloned().collect();
+        let mut ships: Vec<ShipId> = self.moving.keys().cloned().collect();
+        ships.sort_by_key(|ship_id| (!self.planned.contains(ship_id), ship_id.0));
 
         for ship_id in ships.iter().cloned() {
-            self.signal_move(ship_id, &mut moves, &mut HashMap::new());
+            self.signal_move(ship_id, &mut moves, &mut Vec::new());
         }
 
         // Add stay still move for ships which didn't end up moving 
@@ -225,11 +325,310 @@ impl Navi {
         moves
     }
 
+    /// Ranked (position, direction, danger) candidates for a moving ship, best first
+    fn candidates(&self, ship_id: ShipId) -> Vec<(Position, Direction, i32)> {
+        let (position, ref directions) = self.moving[&ship_id];
+        let mut ranked = directions.clone();
+
+        // Ships planning to move can afford any other move as well
+        if ranked.iter().any(|&dir| dir != Direction::Still) {
+            for dir in Direction::get_all_cardinals() {
+                if !ranked.contains(&dir) {
+                    ranked.push(dir);
+                }
+            }
+        }
+
+        if !ranked.contains(&Direction::Still) {
+            ranked.push(Direction::Still);
+        }
+
+        ranked.into_iter()
+            .map(|dir| (self.normalize(&position.directional_offset(dir)), dir))
+            .filter(|&(new_pos, dir)| {
+                // Cells held by ships which are not moving this turn are off limits
+                dir == Direction::Still || self.lane_allows(&position, &new_pos) && match self.get(&new_pos) {
+                    Some(other) => self.moving.contains_key(&other),
+                    None => true,
+                }
+            })
+            .filter_map(|(new_pos, dir)| {
+                // Staying put is always possible, however dangerous
+                match self.danger(ship_id, &new_pos) {
+                    _ if dir == Direction::Still && self.traffic.is_reserved(&new_pos) => Some((new_pos, dir, RESERVED_PENALTY)),
+                    Some(danger) => Some((new_pos, dir, danger)),
+                    None if dir == Direction::Still => Some((new_pos, dir, 0)),
+                    None => None,
+                }
+            })
+            .collect()
+    }
+
+    pub fn collect_matched(&mut self) -> Vec<(ShipId, Direction)> {
+        let mut ships: Vec<ShipId> = self.moving.keys().cloned().collect();
+        ships.sort_by_key(|ship_id| (!self.planned.contains(ship_id), ship_id.0));
+
+        if ships.is_empty() {
+            return Vec::new();
+        }
+
+        // Give each target cell a column, except dropoffs in terminal mode which any number of ships may enter
+        let mut columns: HashMap<Position, usize> = HashMap::new();
+        let mut num_columns = 0;
+        let mut edges: Vec<Vec<Edge>> = Vec::with_capacity(ships.len());
+
+        for &ship_id in &ships {
+            let priority = if self.planned.contains(&ship_id) { PLANNED_PRIORITY } else { UNPLANNED_PRIORITY };
+            let mut row = Vec::new();
+
+            let (position, _) = self.moving[&ship_id];
+            for (rank, (new_pos, dir, danger)) in self.candidates(ship_id).into_iter().enumerate() {
+                let column = if self.terminal && self.dropoffs.contains(&new_pos) {
+                    num_columns += 1;
+                    num_columns - 1
+                } else {
+                    *columns.entry(new_pos).or_insert_with(|| {
+                        num_columns += 1;
+                        num_columns - 1
+                    })
+                };
+
+                // Planned paths already priced in their risk
+                let danger = if self.planned.contains(&ship_id) { 0 } else { danger };
+                let arrival = if self.terminal { None } else { self.traffic.arrival(&position, &new_pos) };
+
+                row.push((column, dir, priority - rank as i32 * RANK_PENALTY - danger, arrival));
+            }
+
+            edges.push(row);
+        }
+
+        // Arrivals around each dropoff go to the highest priority ships they were assigned to, so
+        // ships assigned past the limit lose their arrivals and the fleet is matched again
+        let assignment = loop {
+            let mut weights = vec![INFEASIBLE; ships.len() * num_columns];
+            for (i, row) in edges.iter().enumerate() {
+                for &(column, _, weight, _) in row {
+                    weights[i * num_columns + column] = weight;
+                }
+            }
+
+            let matrix = Matrix::from_vec(ships.len(), num_columns, weights);
+            let assignment = kuhn_munkres(&matrix).1;
+
+            let mut arrivals = self.traffic.arrivals.clone();
+            let mut over = Vec::new();
+            for (i, row) in edges.iter().enumerate() {
+                let arrival = row.iter().find(|edge| edge.0 == assignment[i]).and_then(|edge| edge.3);
+                if let Some(dropoff) = arrival {
+                    let count = arrivals.entry(dropoff).or_insert(0);
+                    if *count >= MAX_ARRIVALS {
+                        over.push(i);
+                    } else {
+                        *count += 1;
+                    }
+                }
+            }
+
+            if over.is_empty() {
+                break assignment;
+            }
+
+            for i in over {
+                edges[i].retain(|edge| edge.3.is_none());
+            }
+        };
+
+        let mut moves = Vec::with_capacity(ships.len());
+        for (i, &ship_id) in ships.iter().enumerate() {
+            let dir = edges[i].iter()
+                .find(|edge| edge.0 == assignment[i])
+                .map(|edge| edge.1)
+                .unwrap_or(Direction::Still);
+
+            moves.push((ship_id, dir));
+        }
+
+        // Vacate every old cell before filling the new ones so rotations keep their occupants
+        for &(ship_id, _) in &moves {
+            let (position, _) = self.moving[&ship_id];
+            if self.get(&position) == Some(ship_id) {
+                self.mark_safe(&position);
+            }
+        }
+
+        for &(ship_id, dir) in &moves {
+            let (position, _) = self.moving[&ship_id];
+            self.mark_unsafe(&position.directional_offset(dir), ship_id);
+            self.traffic.record_move(&position, &position.directional_offset(dir));
+        }
+
+        self.moving.clear();
+        moves
+    }
+
     pub fn normalize(&self, position: &Position) -> Position {
-        let width = self.width as i32;
-        let height = self.height as i32;
-        let x = ((position.x % width) + width) % width;
-        let y = ((position.y % height) + height) % height;
-        Position { x, y }
+        self.torus.normalize(*position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::command::Command;
+    use scenario::{self, Scenario, assert_move};
+    use fixture::Frames;
+
+    #[test]
+    fn parse_names_resolvers() {
+        assert_eq!(Resolver::parse(" Matching"), Some(Resolver::Matching));
+        assert_eq!(Resolver::parse("signal"), Some(Resolver::Signal));
+        assert_eq!(Resolver::parse("bogus"), None);
+        assert_eq!(Resolver::default(), Resolver::Signal);
+    }
+
+    fn collect(map: &str, resolver: Resolver, moves: &[(usize, Direction)]) -> Vec<Command> {
+        let game = Scenario::new(map).game();
+        let mut nav = Navi::new(game.map.width, game.map.height);
+        nav.update_frame(&game);
+        nav.resolver = resolver;
+
+        for &(ship_id, dir) in moves {
+            let ship = &game.ships[&ShipId(ship_id)];
+            nav.nav(ship.id, ship.position, dir);
+        }
+
+        nav.collect_moves().into_iter().map(|(ship_id, dir)| Command::move_ship(ship_id, dir)).collect()
+    }
+
+    #[test]
+    fn ships_swap_places() {
+        for &resolver in &[Resolver::Signal, Resolver::Matching] {
+            let commands = collect("
+                . . . . .
+                . a a . S
+                . . . . .
+            ", resolver, &[(0, Direction::East), (1, Direction::West)]);
+
+            assert_move(&commands, ShipId(0), Direction::East);
+            assert_move(&commands, ShipId(1), Direction::West);
+        }
+    }
+
+    #[test]
+    fn ships_rotate_through_each_others_cells() {
+        // Four ships turning clockwise around a square, each moving into a cell another is leaving
+        let map = "
+            . . . . . .
+            . a a . . .
+            . a a . . S
+            . . . . . .
+        ";
+        let moves = [(0, Direction::East), (1, Direction::South), (3, Direction::West), (2, Direction::North)];
+
+        for &resolver in &[Resolver::Signal, Resolver::Matching] {
+            let game = Scenario::new(map).game();
+            let commands = collect(map, resolver, &moves);
+
+            for &(ship_id, dir) in &moves {
+                assert_move(&commands, ShipId(ship_id), dir);
+            }
+
+            let mut destinations = HashSet::new();
+            for ship in game.ships.values() {
+                let dir = scenario::move_of(&commands, ship.id).unwrap_or(Direction::Still);
+                let destination = game.map.torus.normalize(ship.position.directional_offset(dir));
+                assert!(destinations.insert(destination), "{:?} entered by two ships", destination);
+            }
+        }
+    }
+
+    #[test]
+    fn only_one_ship_enters_a_cell() {
+        for &resolver in &[Resolver::Signal, Resolver::Matching] {
+            let commands = collect("
+                . . . . . .
+                . a . a . S
+                . . . . . .
+            ", resolver, &[(0, Direction::East), (1, Direction::West)]);
+
+            let east = commands.contains(&Command::move_ship(ShipId(0), Direction::East));
+            let west = commands.contains(&Command::move_ship(ShipId(1), Direction::West));
+            assert!(east != west, "Both or neither ship entered the cell");
+        }
+    }
+
+    #[test]
+    fn ship_stays_off_enemy() {
+        for &resolver in &[Resolver::Signal, Resolver::Matching] {
+            let commands = collect("
+                . . . . . .
+                . a b . . S
+                . . . . . S[1]
+            ", resolver, &[(0, Direction::East)]);
+
+            assert!(!commands.contains(&Command::move_ship(ShipId(0), Direction::East)));
+        }
+    }
+
+    #[test]
+    fn arrivals_go_to_ships_that_take_them() {
+        // Ship 0 could arrive at the shipyard but heads away, leaving both arrivals to ships 1 and 2
+        let commands = collect("
+            . . . . . . .
+            . . . a . . .
+            . . . . . . .
+            . a . S . a .
+            . . . . . . .
+            . . . . . . .
+            . . . . . . .
+        ", Resolver::Matching, &[(0, Direction::North), (1, Direction::East), (2, Direction::West)]);
+
+        assert_move(&commands, ShipId(0), Direction::North);
+        assert_move(&commands, ShipId(1), Direction::East);
+        assert_move(&commands, ShipId(2), Direction::West);
+    }
+
+    #[test]
+    fn arrivals_past_the_limit_go_to_later_ships_last() {
+        // Three ships arriving at once, only two may
+        let commands = collect("
+            . . . . . . .
+            . . . a . . .
+            . . . . . . .
+            . a . S . a .
+            . . . . . . .
+            . . . . . . .
+            . . . . . . .
+        ", Resolver::Matching, &[(0, Direction::South), (1, Direction::East), (2, Direction::West)]);
+
+        assert_move(&commands, ShipId(0), Direction::South);
+        assert_move(&commands, ShipId(1), Direction::East);
+        assert!(!commands.contains(&Command::move_ship(ShipId(2), Direction::West)));
+    }
+
+    #[test]
+    fn danger_penalises_rather_than_reorders() {
+        let map = |cargo: usize| format!("
+            . . . . . .
+            . . . . . .
+            . a[{}] . b . .
+            . . . . . S
+            . . . . . S[1]
+        ", cargo);
+
+        for &(cargo, expected) in &[(100, Direction::East), (900, Direction::North)] {
+            let game = Scenario::new(&map(cargo)).game();
+            let mut nav = Navi::new(game.map.width, game.map.height);
+            nav.update_frame(&game);
+            nav.resolver = Resolver::Signal;
+
+            // East is preferred but ends next to the enemy
+            let ship = &game.ships[&ShipId(0)];
+            nav.ranked(ship.id, ship.position, vec![Direction::East, Direction::North, Direction::Still]);
+
+            let commands: Vec<Command> = nav.collect_moves().into_iter().map(|(ship_id, dir)| Command::move_ship(ship_id, dir)).collect();
+            assert_move(&commands, ShipId(0), expected);
+        }
     }
 }
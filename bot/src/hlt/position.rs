@@ -1,6 +1,6 @@
 use hlt::direction::Direction;
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -8,7 +8,7 @@ pub struct Position {
 
 impl Position {
     pub fn new() -> Position {
-        Position { x: 0, y: 0 }
+        Position::default()
     }
 
     pub fn directional_offset(&self, d: Direction) -> Position {
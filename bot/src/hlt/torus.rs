@@ -0,0 +1,243 @@
+use hlt::direction::Direction;
+use hlt::position::Position;
+
+/// Geometry of a map which wraps around at every edge
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Torus {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Torus {
+    pub fn new(width: usize, height: usize) -> Torus {
+        Torus { width, height }
+    }
+
+    pub fn size(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn normalize(&self, position: Position) -> Position {
+        let width = self.width as i32;
+        let height = self.height as i32;
+        let x = ((position.x % width) + width) % width;
+        let y = ((position.y % height) + height) % height;
+        Position { x, y }
+    }
+
+    /// Index of a cell in row major order
+    pub fn index(&self, position: Position) -> usize {
+        let position = self.normalize(position);
+        position.y as usize * self.width + position.x as usize
+    }
+
+    pub fn position(&self, index: usize) -> Position {
+        Position { x: (index % self.width) as i32, y: (index / self.width) as i32 }
+    }
+
+    /// Shortest signed offset from source to target, positive half-way offsets
+    /// preferred when both ways around are equally short
+    pub fn offset(&self, source: Position, target: Position) -> (i32, i32) {
+        let source = self.normalize(source);
+        let target = self.normalize(target);
+
+        (
+            Torus::wrap(target.x - source.x, self.width as i32),
+            Torus::wrap(target.y - source.y, self.height as i32),
+        )
+    }
+
+    pub fn distance(&self, source: Position, target: Position) -> usize {
+        let (dx, dy) = self.offset(source, target);
+        (dx.abs() + dy.abs()) as usize
+    }
+
+    /// Every direction which brings source one step closer to target,
+    /// horizontal moves first, or none when they are the same cell
+    pub fn directions(&self, source: Position, target: Position) -> Vec<Direction> {
+        let (dx, dy) = self.offset(source, target);
+        let mut directions = Vec::new();
+
+        if dx != 0 {
+            directions.push(if dx > 0 { Direction::East } else { Direction::West });
+            if dx * 2 == self.width as i32 {
+                directions.push(Direction::West);
+            }
+        }
+
+        if dy != 0 {
+            directions.push(if dy > 0 { Direction::South } else { Direction::North });
+            if dy * 2 == self.height as i32 {
+                directions.push(Direction::North);
+            }
+        }
+
+        directions
+    }
+
+    /// Cells at exactly distance radius from center, each listed once
+    pub fn ring(&self, center: Position, radius: usize) -> Vec<Position> {
+        self.offsets(radius)
+            .filter(|&(dx, dy)| (dx.abs() + dy.abs()) as usize == radius)
+            .map(|(dx, dy)| self.normalize(Position { x: center.x + dx, y: center.y + dy }))
+            .collect()
+    }
+
+    /// Cells within distance radius of center, each listed once
+    pub fn ball(&self, center: Position, radius: usize) -> Vec<Position> {
+        self.offsets(radius)
+            .map(|(dx, dy)| self.normalize(Position { x: center.x + dx, y: center.y + dy }))
+            .collect()
+    }
+
+    /// Offsets within radius, limited to the one shortest offset for each cell
+    fn offsets(&self, radius: usize) -> impl Iterator<Item = (i32, i32)> {
+        let r = radius as i32;
+        let (xs, ys) = (Torus::span(self.width, r), Torus::span(self.height, r));
+
+        xs.flat_map(move |dx| {
+            let ry = r - dx.abs();
+            ys.clone().filter(move |dy| dy.abs() <= ry).map(move |dy| (dx, dy))
+        })
+    }
+
+    /// Offsets along one axis of the given length which are within radius
+    fn span(length: usize, radius: i32) -> std::ops::RangeInclusive<i32> {
+        let length = length as i32;
+        (-(length - 1) / 2).max(-radius)..=(length / 2).min(radius)
+    }
+
+    fn wrap(delta: i32, length: i32) -> i32 {
+        let delta = ((delta % length) + length) % length;
+        if delta * 2 > length {
+            delta - length
+        } else {
+            delta
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const SIZES: [(usize, usize); 5] = [(5, 8), (8, 5), (7, 3), (12, 9), (1, 4)];
+
+    fn cells(torus: &Torus) -> Vec<Position> {
+        (0..torus.size()).map(|i| torus.position(i)).collect()
+    }
+
+    fn brute_distance(torus: &Torus, a: Position, b: Position) -> usize {
+        let dx = (a.x - b.x).unsigned_abs() as usize;
+        let dy = (a.y - b.y).unsigned_abs() as usize;
+        dx.min(torus.width - dx) + dy.min(torus.height - dy)
+    }
+
+    #[test]
+    fn normalize_wraps_every_edge() {
+        for &(w, h) in &SIZES {
+            let torus = Torus::new(w, h);
+            let (wi, hi) = (w as i32, h as i32);
+
+            for cell in cells(&torus) {
+                for &(kx, ky) in &[(-2, 0), (-1, 0), (1, 0), (0, -1), (0, 1), (0, 3), (-1, -1), (2, 2)] {
+                    let shifted = Position { x: cell.x + kx * wi, y: cell.y + ky * hi };
+                    assert_eq!(torus.normalize(shifted), cell);
+                }
+            }
+
+            assert_eq!(torus.normalize(Position { x: -1, y: -1 }), Position { x: wi - 1, y: hi - 1 });
+            assert_eq!(torus.normalize(Position { x: wi, y: hi }), Position { x: 0, y: 0 });
+        }
+    }
+
+    #[test]
+    fn index_round_trips() {
+        for &(w, h) in &SIZES {
+            let torus = Torus::new(w, h);
+            for (i, cell) in cells(&torus).into_iter().enumerate() {
+                assert_eq!(torus.index(cell), i);
+                assert_eq!(torus.position(i), cell);
+                assert_eq!(torus.index(Position { x: cell.x - w as i32, y: cell.y + h as i32 }), i);
+            }
+        }
+    }
+
+    #[test]
+    fn offsets_are_shortest_and_land_on_target() {
+        for &(w, h) in &SIZES {
+            let torus = Torus::new(w, h);
+            for a in cells(&torus) {
+                for b in cells(&torus) {
+                    let (dx, dy) = torus.offset(a, b);
+
+                    assert_eq!(torus.normalize(Position { x: a.x + dx, y: a.y + dy }), b);
+                    assert!(dx.abs() * 2 <= w as i32 && dy.abs() * 2 <= h as i32);
+                    assert_eq!(torus.distance(a, b), brute_distance(&torus, a, b));
+                    assert_eq!(torus.distance(a, b), torus.distance(b, a));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn directions_step_closer() {
+        for &(w, h) in &SIZES {
+            let torus = Torus::new(w, h);
+            for a in cells(&torus) {
+                for b in cells(&torus) {
+                    let distance = torus.distance(a, b);
+                    let directions = torus.directions(a, b);
+
+                    let expected: Vec<Direction> = Direction::get_all_cardinals().into_iter()
+                        .filter(|&dir| torus.distance(a.directional_offset(dir), b) + 1 == distance)
+                        .collect();
+
+                    assert_eq!(directions.len(), expected.len(), "{:?} -> {:?} on {}x{}", (a.x, a.y), (b.x, b.y), w, h);
+                    for dir in expected {
+                        assert!(directions.contains(&dir));
+                    }
+
+                    assert_eq!(directions.is_empty(), a == b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn directions_wrap_at_edges() {
+        let torus = Torus::new(10, 6);
+
+        assert_eq!(torus.directions(Position { x: 0, y: 2 }, Position { x: 9, y: 2 }), vec![Direction::West]);
+        assert_eq!(torus.directions(Position { x: 9, y: 2 }, Position { x: 0, y: 2 }), vec![Direction::East]);
+        assert_eq!(torus.directions(Position { x: 3, y: 0 }, Position { x: 3, y: 5 }), vec![Direction::North]);
+        assert_eq!(torus.directions(Position { x: 3, y: 5 }, Position { x: 3, y: 0 }), vec![Direction::South]);
+        assert_eq!(torus.directions(Position { x: 0, y: 0 }, Position { x: 5, y: 3 }),
+                   vec![Direction::East, Direction::West, Direction::South, Direction::North]);
+    }
+
+    #[test]
+    fn balls_and_rings_cover_each_cell_once() {
+        for &(w, h) in &SIZES {
+            let torus = Torus::new(w, h);
+            for center in cells(&torus) {
+                for radius in 0..(w + h) {
+                    let ball_cells = torus.ball(center, radius);
+                    let ring_cells = torus.ring(center, radius);
+
+                    let ball: HashSet<Position> = ball_cells.iter().cloned().collect();
+                    let ring: HashSet<Position> = ring_cells.iter().cloned().collect();
+                    assert_eq!(ball.len(), ball_cells.len());
+                    assert_eq!(ring.len(), ring_cells.len());
+
+                    for cell in cells(&torus) {
+                        let distance = torus.distance(center, cell);
+                        assert_eq!(ball.contains(&cell), distance <= radius);
+                        assert_eq!(ring.contains(&cell), distance == radius);
+                    }
+                }
+            }
+        }
+    }
+}
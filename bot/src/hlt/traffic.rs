@@ -0,0 +1,250 @@
+use hlt::direction::Direction;
+use hlt::game::Game;
+use hlt::position::Position;
+use hlt::torus::Torus;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Ships allowed to enter the cells around a dropoff each turn
+pub const MAX_ARRIVALS: usize = 2;
+/// Distance within which ships heading home decide which lanes are inbound
+const APPROACH_RADIUS: i32 = 12;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Lane {
+    /// Ships may only enter the dropoff from this cell
+    Inbound(Position),
+    /// Ships may only leave the dropoff into this cell
+    Outbound(Position),
+}
+
+impl Lane {
+    pub fn dropoff(&self) -> Position {
+        match *self {
+            Lane::Inbound(dropoff) => dropoff,
+            Lane::Outbound(dropoff) => dropoff,
+        }
+    }
+
+    /// Whether lanes forbid moving between two cells, given the lane of each
+    pub fn forbids(from: (Position, Option<Lane>), to: (Position, Option<Lane>)) -> bool {
+        from.1 == Some(Lane::Outbound(to.0)) || to.1 == Some(Lane::Inbound(from.0))
+    }
+
+    /// Dropoff whose surrounding cells a move enters from outside, if any
+    pub fn arrival(from: (Position, Option<Lane>), to: (Position, Option<Lane>)) -> Option<Position> {
+        let dropoff = to.1?.dropoff();
+        if from.0 == dropoff || from.1.map(|lane| lane.dropoff()) == Some(dropoff) {
+            None
+        } else {
+            Some(dropoff)
+        }
+    }
+}
+
+pub struct Traffic {
+    pub torus: Torus,
+    pub lanes: HashMap<Position, Lane>,
+    pub reserved: HashSet<Position>,
+    pub arrivals: HashMap<Position, usize>,
+}
+
+impl Traffic {
+    pub fn new(width: usize, height: usize) -> Traffic {
+        Traffic {
+            torus: Torus::new(width, height),
+            lanes: HashMap::new(),
+            reserved: HashSet::new(),
+            arrivals: HashMap::new(),
+        }
+    }
+
+    pub fn update_frame(&mut self, game: &Game) {
+        self.lanes.clear();
+        self.reserved.clear();
+        self.arrivals.clear();
+
+        let me = &game.players[game.my_id.0];
+        let dropoffs: Vec<Position> = std::iter::once(me.shipyard.position)
+            .chain(me.dropoff_ids.iter().map(|id| game.dropoffs[id].position))
+            .collect();
+
+        let returning: Vec<Position> = me.ship_ids.iter()
+            .map(|id| &game.ships[id])
+            .filter(|ship| ship.halite >= game.constants.max_halite / 2)
+            .map(|ship| ship.position)
+            .collect();
+
+        for &dropoff in &dropoffs {
+            // Ships heading home come in along the axis most of them approach from
+            let mut vertical = 0;
+            let mut horizontal = 0;
+            for &pos in &returning {
+                let (dx, dy) = self.torus.offset(dropoff, pos);
+                if dx.abs() + dy.abs() <= APPROACH_RADIUS {
+                    if dy.abs() > dx.abs() {
+                        vertical += 1;
+                    } else {
+                        horizontal += 1;
+                    }
+                }
+            }
+
+            let inbound = if vertical > horizontal {
+                [Direction::North, Direction::South]
+            } else {
+                [Direction::East, Direction::West]
+            };
+
+            for dir in Direction::get_all_cardinals() {
+                let pos = self.normalize(&dropoff.directional_offset(dir));
+                let lane = if inbound.contains(&dir) {
+                    Lane::Inbound(dropoff)
+                } else {
+                    Lane::Outbound(dropoff)
+                };
+
+                self.lanes.entry(pos).or_insert(lane);
+            }
+        }
+    }
+
+    pub fn lane(&self, position: &Position) -> Option<Lane> {
+        self.lanes.get(&self.normalize(position)).cloned()
+    }
+
+    /// Keep a cell clear of our ships this turn
+    pub fn reserve(&mut self, position: &Position) {
+        let position = self.normalize(position);
+        self.reserved.insert(position);
+    }
+
+    pub fn release(&mut self, position: &Position) {
+        let position = self.normalize(position);
+        self.reserved.remove(&position);
+    }
+
+    pub fn is_reserved(&self, position: &Position) -> bool {
+        self.reserved.contains(&self.normalize(position))
+    }
+
+    /// Dropoff whose surrounding cells a move enters, if any
+    pub fn arrival(&self, from: &Position, to: &Position) -> Option<Position> {
+        let from = self.normalize(from);
+        let to = self.normalize(to);
+        Lane::arrival((from, self.lane(&from)), (to, self.lane(&to)))
+    }
+
+    /// Whether a move respects the lanes, reservations and the arrival limit
+    pub fn allows(&self, from: &Position, to: &Position) -> bool {
+        let from = self.normalize(from);
+        let to = self.normalize(to);
+
+        if Lane::forbids((from, self.lane(&from)), (to, self.lane(&to))) || self.is_reserved(&to) {
+            return false;
+        }
+
+        match self.arrival(&from, &to) {
+            Some(dropoff) => self.arrivals.get(&dropoff).cloned().unwrap_or(0) < MAX_ARRIVALS,
+            None => true,
+        }
+    }
+
+    pub fn record_move(&mut self, from: &Position, to: &Position) {
+        if let Some(dropoff) = self.arrival(from, to) {
+            *self.arrivals.entry(dropoff).or_insert(0) += 1;
+        }
+    }
+
+    pub fn normalize(&self, position: &Position) -> Position {
+        self.torus.normalize(*position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scenario::Scenario;
+    use fixture::Frames;
+
+    fn traffic(map: &str) -> (Traffic, Position) {
+        let game = Scenario::new(map).game();
+        let mut traffic = Traffic::new(game.map.width, game.map.height);
+        traffic.update_frame(&game);
+        (traffic, game.players[0].shipyard.position)
+    }
+
+    #[test]
+    fn lanes_follow_the_approach_of_loaded_ships() {
+        // Loaded ships above and below, an empty one to the side
+        let (traffic, shipyard) = traffic("
+            . . . a[900] . . .
+            . . . . . . .
+            . . . . . . .
+            a . . S . . .
+            . . . . . . .
+            . . . . . . .
+            . . . a[800] . . .
+        ");
+
+        for dir in Direction::get_all_cardinals() {
+            let pos = traffic.normalize(&shipyard.directional_offset(dir));
+            let expected = match dir {
+                Direction::North | Direction::South => Lane::Inbound(shipyard),
+                _ => Lane::Outbound(shipyard),
+            };
+            assert!(traffic.lane(&pos) == Some(expected));
+        }
+
+        let (north, east) = (shipyard.directional_offset(Direction::North), shipyard.directional_offset(Direction::East));
+        let far_north = north.directional_offset(Direction::North);
+        assert!(traffic.allows(&far_north, &north) && traffic.allows(&north, &shipyard));
+        assert!(!traffic.allows(&east, &shipyard), "Outbound lanes can't be used to come home");
+        assert!(!traffic.allows(&shipyard, &north), "Inbound lanes can't be used to leave");
+    }
+
+    #[test]
+    fn arrivals_are_metered() {
+        let (mut traffic, shipyard) = traffic("
+            . . . . .
+            . . . . .
+            . . S . .
+            . . . . .
+            . . . . .
+        ");
+
+        // With no loaded ships, lanes default to east and west inbound
+        let west = shipyard.directional_offset(Direction::West);
+        let far_west = west.directional_offset(Direction::West);
+        let east = shipyard.directional_offset(Direction::East);
+        let far_east = east.directional_offset(Direction::East);
+        assert_eq!(traffic.arrival(&far_west, &west), Some(shipyard));
+        assert_eq!(traffic.arrival(&west, &shipyard), None);
+
+        for _ in 0..MAX_ARRIVALS {
+            assert!(traffic.allows(&far_east, &east));
+            traffic.record_move(&far_west, &west);
+        }
+
+        assert!(!traffic.allows(&far_east, &east));
+        assert!(traffic.allows(&west, &shipyard), "Ships already next to the dropoff aren't arrivals");
+    }
+
+    #[test]
+    fn reserved_cells_are_closed_until_released() {
+        let (mut traffic, shipyard) = traffic("
+            . . . . .
+            . . S . .
+            . . . . .
+        ");
+        let west = shipyard.directional_offset(Direction::West);
+
+        traffic.reserve(&shipyard);
+        assert!(traffic.is_reserved(&shipyard));
+        assert!(!traffic.allows(&west, &shipyard));
+
+        traffic.release(&shipyard);
+        assert!(!traffic.is_reserved(&shipyard));
+        assert!(traffic.allows(&west, &shipyard));
+    }
+}
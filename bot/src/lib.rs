@@ -0,0 +1,28 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate pathfinding;
+extern crate im_rc;
+
+pub mod hlt;
+pub mod state;
+pub mod step;
+pub mod grid;
+pub mod action;
+pub mod cost;
+pub mod timeline;
+pub mod stats;
+pub mod profile;
+pub mod collision;
+pub mod fallback;
+pub mod divergence;
+#[cfg(any(test, feature = "testing"))]
+pub mod fixture;
+#[cfg(any(test, feature = "testing"))]
+pub mod scenario;
+pub mod rng;
+pub mod rollout;
+pub mod overlay;
+pub mod trace;
+pub mod metrics;
+pub mod bot;
+pub mod replay;
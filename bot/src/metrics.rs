@@ -0,0 +1,282 @@
+use hlt::*;
+use collision::Collision;
+use stats::Stats;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Why one of our ships sank
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Loss {
+    /// Collided with an enemy ship
+    Enemy,
+    /// Collided with only our own ships
+    Friendly,
+    /// Collided on one of our own structures, so its cargo was kept
+    Structure,
+    /// Vanished without a collision we could place
+    Unknown,
+}
+
+const LOSSES: [Loss; 4] = [Loss::Enemy, Loss::Friendly, Loss::Structure, Loss::Unknown];
+
+impl Loss {
+    fn name(&self) -> &'static str {
+        match *self {
+            Loss::Enemy => "enemy",
+            Loss::Friendly => "friendly",
+            Loss::Structure => "structure",
+            Loss::Unknown => "unknown",
+        }
+    }
+}
+
+/// One of our ships as it was last frame
+#[derive(Copy, Clone)]
+struct Snapshot {
+    pos: Position,
+    halite: usize,
+    /// Halite it would burn leaving its cell
+    burn: usize,
+    inspired: bool,
+}
+
+/// What our fleet did over the game, reported when the game ends
+#[derive(Default)]
+pub struct Metrics {
+    last: HashMap<ShipId, Snapshot>,
+    dropoff_ids: HashSet<DropoffId>,
+    pub mined: usize,
+    pub inspired_mined: usize,
+    pub returned: usize,
+    pub ships_built: usize,
+    pub losses: HashMap<Loss, usize>,
+    /// Turn and position of each dropoff we built
+    pub dropoffs: Vec<(usize, Position)>,
+    pub ship_turns: usize,
+    /// Turns a ship stayed still without mining anything
+    pub idle: usize,
+    /// Paths dropped because their first action could no longer be applied
+    pub poisoned: usize,
+    /// Paths cut short because a later action could no longer be applied
+    pub truncated: usize,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Account for everything that happened to our ships since the last frame
+    pub fn update(&mut self, game: &Game, crashes: &[Collision]) {
+        let me = &game.players[game.my_id.0];
+        let structures: HashSet<Position> = std::iter::once(me.shipyard.position)
+            .chain(game.dropoffs.values().filter(|d| d.owner == game.my_id).map(|d| d.position))
+            .collect();
+
+        for ship in game.ships.values().filter(|ship| ship.owner == game.my_id) {
+            self.ship_turns += 1;
+
+            match self.last.get(&ship.id) {
+                None => self.ships_built += 1,
+                Some(last) if last.pos == ship.position => {
+                    let gained = ship.halite.saturating_sub(last.halite);
+                    if gained == 0 {
+                        self.idle += 1;
+                    }
+
+                    self.mined += gained;
+                    if last.inspired {
+                        self.inspired_mined += gained;
+                    }
+                }
+                Some(last) => if structures.contains(&ship.position) {
+                    self.returned += last.halite.saturating_sub(last.burn);
+                },
+            }
+        }
+
+        for crash in crashes {
+            let ours: Vec<ShipId> = crash.ships.iter()
+                .filter(|&&(owner, _)| owner == game.my_id)
+                .map(|&(_, ship_id)| ship_id)
+                .collect();
+
+            if ours.is_empty() {
+                continue;
+            }
+
+            let cause = if structures.contains(&crash.pos) {
+                Loss::Structure
+            } else if crash.ships.iter().any(|&(owner, _)| owner != game.my_id) {
+                Loss::Enemy
+            } else if ours.len() > 1 {
+                Loss::Friendly
+            } else {
+                Loss::Unknown
+            };
+
+            *self.losses.entry(cause).or_insert(0) += ours.len();
+
+            // Cargo of ships sunk on our structures is deposited
+            if cause == Loss::Structure {
+                self.returned += ours.iter()
+                    .filter_map(|ship_id| self.last.get(ship_id))
+                    .map(|last| last.halite.saturating_sub(last.burn))
+                    .sum::<usize>();
+            }
+        }
+
+        for dropoff in game.dropoffs.values().filter(|d| d.owner == game.my_id) {
+            if self.dropoff_ids.insert(dropoff.id) {
+                self.dropoffs.push((game.turn_number, dropoff.position));
+            }
+        }
+
+        let enemies: Vec<Position> = game.ships.values()
+            .filter(|ship| ship.owner != game.my_id)
+            .map(|ship| ship.position)
+            .collect();
+
+        let constants = &game.constants;
+        self.last = game.ships.values()
+            .filter(|ship| ship.owner == game.my_id)
+            .map(|ship| {
+                let pos = ship.position;
+                let nearby = enemies.iter().filter(|&&e| game.map.torus.distance(pos, e) <= constants.inspiration_radius).count();
+
+                let snapshot = Snapshot {
+                    pos,
+                    halite: ship.halite,
+                    burn: game.map.at_position(&pos).halite / constants.move_cost_ratio,
+                    inspired: constants.inspiration_enabled && nearby >= constants.inspiration_ship_count,
+                };
+
+                (ship.id, snapshot)
+            })
+            .collect();
+    }
+
+    /// Count the paths the timeline could not follow this turn
+    pub fn plan(&mut self, poisoned: &HashMap<ShipId, usize>) {
+        self.poisoned += poisoned.values().filter(|&&t| t == 0).count();
+        self.truncated += poisoned.values().filter(|&&t| t > 0).count();
+    }
+
+    /// Everything as a JSON object, with the game's timings
+    pub fn report(&self, stats: &Stats) -> String {
+        let inspired_share = if self.mined == 0 { 0.0 } else { self.inspired_mined as f64 / self.mined as f64 };
+
+        let losses: Vec<String> = LOSSES.iter()
+            .map(|loss| format!("\"{}\": {}", loss.name(), self.losses.get(loss).cloned().unwrap_or(0)))
+            .collect();
+
+        let dropoffs: Vec<String> = self.dropoffs.iter()
+            .map(|&(turn, pos)| format!("{{ \"turn\": {}, \"x\": {}, \"y\": {} }}", turn, pos.x, pos.y))
+            .collect();
+
+        let fields = [
+            format!("\"mined\": {}", self.mined),
+            format!("\"returned\": {}", self.returned),
+            format!("\"inspired_mined\": {}", self.inspired_mined),
+            format!("\"inspired_share\": {:.4}", inspired_share),
+            format!("\"ships_built\": {}", self.ships_built),
+            format!("\"ships_lost\": {{ {} }}", losses.join(", ")),
+            format!("\"dropoffs\": [{}]", dropoffs.join(", ")),
+            format!("\"ship_turns\": {}", self.ship_turns),
+            format!("\"idle_ship_turns\": {}", self.idle),
+            format!("\"poisoned_paths\": {}", self.poisoned),
+            format!("\"truncated_paths\": {}", self.truncated),
+            format!("\"timing\": {}", stats.report()),
+        ];
+
+        format!("{{\n  {}\n}}\n", fields.join(",\n  "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixture::{Fixture, Frames};
+
+    #[test]
+    fn mining_returns_and_losses_are_counted() {
+        let mut game = Fixture::new(16, 16, 2, 3).game();
+        let shipyard = game.players[0].shipyard.position;
+        let mut metrics = Metrics::new();
+        metrics.update(&game, &[]);
+
+        let ours: Vec<ShipId> = {
+            let mut ids = game.players[0].ship_ids.clone();
+            ids.sort_by_key(|id| id.0);
+            ids
+        };
+        assert_eq!(metrics.ships_built, 3);
+
+        // One ship mines, one returns to the shipyard and one is sunk by an enemy
+        let (miner, returner, sunk) = (ours[0], ours[1], ours[2]);
+        let cargo = game.ships[&returner].halite;
+        let burn = game.map.at_position(&game.ships[&returner].position).halite / game.constants.move_cost_ratio;
+        let mined_before = game.ships[&miner].halite;
+
+        game.ships.get_mut(&miner).unwrap().halite = mined_before + 20;
+        {
+            let ship = game.ships.get_mut(&returner).unwrap();
+            ship.position = shipyard;
+            ship.halite = 0;
+        }
+        let sunk_pos = game.ships.remove(&sunk).unwrap().position;
+        let enemy = game.players[1].ship_ids[0];
+        let crash = Collision { turn: 51, pos: sunk_pos, ships: vec![(PlayerId(0), sunk), (PlayerId(1), enemy)], dropped: 0 };
+
+        metrics.update(&game, &[crash]);
+
+        assert_eq!(metrics.mined, 20);
+        assert_eq!(metrics.returned, cargo - burn);
+        assert_eq!(metrics.losses[&Loss::Enemy], 1);
+        assert_eq!(metrics.ship_turns, 5);
+        assert_eq!(metrics.idle, 0);
+
+        let report = metrics.report(&Stats::new());
+        assert!(report.contains("\"ships_lost\": { \"enemy\": 1, \"friendly\": 0, \"structure\": 0, \"unknown\": 0 }"));
+    }
+
+    #[test]
+    fn losses_are_put_down_to_their_cause() {
+        let mut game = Fixture::new(16, 16, 2, 4).game();
+        let shipyard = game.players[0].shipyard.position;
+        let mut metrics = Metrics::new();
+        metrics.update(&game, &[]);
+
+        let ours: Vec<ShipId> = {
+            let mut ids = game.players[0].ship_ids.clone();
+            ids.sort_by_key(|id| id.0);
+            ids
+        };
+
+        // Two of ours sink each other, one sinks on the shipyard and one vanishes alone
+        let open = game.map.normalize(&shipyard.directional_offset(Direction::East));
+        let docked = ours[2];
+        let cargo = game.ships[&docked].halite;
+        let burn = game.map.at_position(&game.ships[&docked].position).halite / game.constants.move_cost_ratio;
+        assert!(cargo > burn);
+
+        for ship_id in &ours {
+            game.ships.remove(ship_id);
+        }
+
+        let crashes = [
+            Collision { turn: 51, pos: open, ships: vec![(PlayerId(0), ours[0]), (PlayerId(0), ours[1])], dropped: 0 },
+            Collision { turn: 51, pos: shipyard, ships: vec![(PlayerId(0), docked)], dropped: 0 },
+            Collision { turn: 51, pos: open, ships: vec![(PlayerId(0), ours[3])], dropped: 0 },
+        ];
+        metrics.update(&game, &crashes);
+
+        assert_eq!(metrics.losses[&Loss::Friendly], 2);
+        assert_eq!(metrics.losses[&Loss::Structure], 1);
+        assert_eq!(metrics.losses[&Loss::Unknown], 1);
+        assert!(!metrics.losses.contains_key(&Loss::Enemy));
+
+        // Cargo sunk on our shipyard is banked all the same
+        assert_eq!(metrics.returned, cargo - burn);
+    }
+}
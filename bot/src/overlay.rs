@@ -0,0 +1,150 @@
+use hlt::*;
+use std::env;
+
+/// Comma separated heatmap layers to draw, or `all`; none are drawn by default
+const LAYERS_VAR: &str = "BOT_LOG_LAYERS";
+/// Colours given to dropoffs in the assignment layer, in dropoff order
+const PALETTE: [(u8, u8, u8); 6] = [
+    (0x1f, 0x77, 0xb4),
+    (0xff, 0x7f, 0x0e),
+    (0x2c, 0xa0, 0x2c),
+    (0xd6, 0x27, 0x28),
+    (0x94, 0x67, 0xbd),
+    (0x17, 0xbe, 0xcf),
+];
+
+lazy_static! {
+    static ref ENABLED: Vec<Layer> = Layer::parse(&env::var(LAYERS_VAR).unwrap_or_default());
+}
+
+/// Heatmap drawn over the map in the fluorine viewer
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Layer {
+    /// Halite kernel the dropoff planner ranks cells by
+    Richness,
+    /// Cells forecast to be inspired next turn
+    Inspired,
+    /// Risk of enemies forecast to be at or next to a cell next turn
+    Danger,
+    /// Timesteps each cell is occupied by our planned paths
+    Occupancy,
+    /// Dropoff each ship is assigned to return to
+    Assignment,
+}
+
+const LAYERS: [Layer; 5] = [
+    Layer::Richness,
+    Layer::Inspired,
+    Layer::Danger,
+    Layer::Occupancy,
+    Layer::Assignment,
+];
+
+impl Layer {
+    fn name(&self) -> &'static str {
+        match *self {
+            Layer::Richness => "richness",
+            Layer::Inspired => "inspired",
+            Layer::Danger => "danger",
+            Layer::Occupancy => "occupancy",
+            Layer::Assignment => "assignment",
+        }
+    }
+
+    fn category(&self) -> Category {
+        match *self {
+            Layer::Richness | Layer::Assignment => Category::Dropoff,
+            Layer::Inspired | Layer::Occupancy => Category::Planner,
+            Layer::Danger => Category::Navi,
+        }
+    }
+
+    /// Colours of the lowest and highest values
+    fn ramp(&self) -> ((u8, u8, u8), (u8, u8, u8)) {
+        match *self {
+            Layer::Richness => ((0x20, 0x20, 0x60), (0xff, 0xe0, 0x40)),
+            Layer::Inspired => ((0x20, 0x40, 0x20), (0x40, 0xff, 0x80)),
+            Layer::Danger => ((0x40, 0x20, 0x20), (0xff, 0x30, 0x30)),
+            Layer::Occupancy => ((0x20, 0x30, 0x40), (0x40, 0xc0, 0xff)),
+            // Drawn by group from the palette instead
+            Layer::Assignment => (PALETTE[0], PALETTE[0]),
+        }
+    }
+
+    fn parse(spec: &str) -> Vec<Layer> {
+        let names: Vec<String> = spec.split(',').map(|name| name.trim().to_lowercase()).collect();
+
+        LAYERS.iter()
+            .filter(|layer| names.iter().any(|name| name == "all" || name == layer.name()))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the layer is drawn, to skip computing it otherwise
+    pub fn enabled(&self) -> bool {
+        ENABLED.contains(self) && Log::enabled(Level::Debug, self.category())
+    }
+}
+
+/// Colour each cell along the layer's ramp, scaled to the largest value;
+/// cells with no value are left out to keep the log small
+pub fn heatmap(layer: Layer, values: impl IntoIterator<Item = (Position, f32)>) {
+    if !layer.enabled() {
+        return;
+    }
+
+    let values: Vec<(Position, f32)> = values.into_iter().filter(|&(_, value)| value > 0.0).collect();
+    let max = values.iter().fold(0.0f32, |max, &(_, value)| max.max(value));
+    let (low, high) = layer.ramp();
+
+    for (pos, value) in values {
+        let colour = shade(low, high, value / max);
+        Log::log(layer.category(), pos, format_args!("{}:{}", layer.name(), value), colour);
+    }
+}
+
+/// Colour each cell by the group it belongs to, naming the group in the message
+pub fn groups(layer: Layer, cells: impl IntoIterator<Item = (Position, usize, String)>) {
+    if !layer.enabled() {
+        return;
+    }
+
+    for (pos, group, name) in cells {
+        let (r, g, b) = PALETTE[group % PALETTE.len()];
+        Log::log(layer.category(), pos, format_args!("{}:{}", layer.name(), name), hex(r, g, b));
+    }
+}
+
+/// Colour a fraction of the way from low to high
+fn shade(low: (u8, u8, u8), high: (u8, u8, u8), fraction: f32) -> String {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * fraction).round() as u8;
+
+    hex(mix(low.0, high.0), mix(low.1, high.1), mix(low.2, high.2))
+}
+
+fn hex(r: u8, g: u8, b: u8) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keeps_known_layers() {
+        assert_eq!(Layer::parse(""), vec![]);
+        assert_eq!(Layer::parse("Danger, richness,bogus"), vec![Layer::Richness, Layer::Danger]);
+        assert_eq!(Layer::parse("all"), LAYERS.to_vec());
+    }
+
+    #[test]
+    fn shade_runs_along_ramp() {
+        let (low, high) = ((0, 0, 0), (200, 100, 255));
+
+        assert_eq!(shade(low, high, 0.0), "#000000");
+        assert_eq!(shade(low, high, 0.5), "#643280");
+        assert_eq!(shade(low, high, 1.0), "#c864ff");
+        assert_eq!(shade(low, high, 2.0), "#c864ff");
+    }
+}
@@ -0,0 +1,266 @@
+use hlt::*;
+use collision::Collision;
+use std::collections::HashMap;
+
+/// Share of moves within reach of our ships assumed to end next to one
+const BASE_ADJACENCY: f32 = 0.25;
+/// Share of ships assumed to sink in a collision
+const BASE_COLLISION: f32 = 0.1;
+/// Number of observations the base rates are worth
+const PRIOR_WEIGHT: f32 = 10.0;
+const MIN_RISK_FACTOR: f32 = 0.25;
+const MAX_RISK_FACTOR: f32 = 2.0;
+
+#[derive(Clone, Default)]
+pub struct Profile {
+    pub ships_seen: usize,
+    pub opportunities: usize,
+    pub adjacent_moves: usize,
+    pub collisions: usize,
+    pub returns: usize,
+    pub returned_cargo: usize,
+    pub dropoff_turns: Vec<usize>,
+}
+
+impl Profile {
+    /// How often a ship within two cells of ours ends its move next to one
+    pub fn adjacency_rate(&self) -> f32 {
+        (self.adjacent_moves as f32 + PRIOR_WEIGHT * BASE_ADJACENCY) / (self.opportunities as f32 + PRIOR_WEIGHT)
+    }
+
+    /// How often a ship of this player has sunk
+    pub fn collision_rate(&self) -> f32 {
+        (self.collisions as f32 + PRIOR_WEIGHT * BASE_COLLISION) / (self.ships_seen as f32 + PRIOR_WEIGHT)
+    }
+
+    /// Average cargo carried into a dropoff
+    pub fn return_threshold(&self) -> Option<usize> {
+        self.returned_cargo.checked_div(self.returns)
+    }
+
+    /// Average turns between dropoff builds, counting the first from turn 0
+    pub fn dropoff_interval(&self) -> Option<usize> {
+        self.dropoff_turns.last().map(|&last| last / self.dropoff_turns.len())
+    }
+
+    /// Whether the player has gone its usual interval since its last dropoff
+    pub fn dropoff_due(&self, turn: usize) -> bool {
+        match (self.dropoff_turns.last(), self.dropoff_interval()) {
+            (Some(&last), Some(interval)) => turn >= last + interval,
+            _ => false,
+        }
+    }
+
+    /// Multiplier for risk penalties near this player's ships, 1.0 without observations
+    pub fn risk_factor(&self) -> f32 {
+        let adjacency = self.adjacency_rate() / BASE_ADJACENCY;
+        let collisions = self.collision_rate() / BASE_COLLISION;
+
+        (0.5 * adjacency + 0.5 * collisions).clamp(MIN_RISK_FACTOR, MAX_RISK_FACTOR)
+    }
+}
+
+#[derive(Default)]
+pub struct Profiles {
+    profiles: HashMap<PlayerId, Profile>,
+    ships_last: HashMap<ShipId, Ship>,
+    dropoffs_last: HashMap<PlayerId, usize>,
+}
+
+impl Profiles {
+    pub fn new() -> Profiles {
+        Profiles::default()
+    }
+
+    pub fn get(&self, player_id: PlayerId) -> Option<&Profile> {
+        self.profiles.get(&player_id)
+    }
+
+    pub fn risk_factor(&self, player_id: PlayerId) -> f32 {
+        self.get(player_id).map(|p| p.risk_factor()).unwrap_or(1.0)
+    }
+
+    pub fn dropoff_due(&self, player_id: PlayerId, turn: usize) -> bool {
+        self.get(player_id).map(|p| p.dropoff_due(turn)).unwrap_or(false)
+    }
+
+    pub fn update(&mut self, game: &Game, collisions: &[Collision]) {
+        let mine: Vec<Position> = self.ships_last.values()
+            .filter(|ship| ship.owner == game.my_id)
+            .map(|ship| ship.position)
+            .collect();
+
+        let mut structures: HashMap<PlayerId, Vec<Position>> = HashMap::new();
+        for player in &game.players {
+            let positions = std::iter::once(player.shipyard.position)
+                .chain(player.dropoff_ids.iter().map(|id| game.dropoffs[id].position))
+                .collect();
+            structures.insert(player.id, positions);
+        }
+
+        let near = |pos: Position, dist: usize| mine.iter().any(|p| game.map.calculate_distance(&pos, p) <= dist);
+
+        for ship in game.ships.values().filter(|ship| ship.owner != game.my_id) {
+            let profile = self.profiles.entry(ship.owner).or_default();
+
+            let last = match self.ships_last.get(&ship.id) {
+                Some(last) => last,
+                None => {
+                    profile.ships_seen += 1;
+                    continue
+                }
+            };
+
+            // Ship could have moved next to one of ours
+            if near(last.position, 2) {
+                profile.opportunities += 1;
+                if ship.position != last.position && near(ship.position, 1) {
+                    profile.adjacent_moves += 1;
+                }
+            }
+
+            // Cargo emptied on one of the player's structures
+            if ship.halite == 0 && last.halite > 0 && structures[&ship.owner].contains(&ship.position) {
+                profile.returns += 1;
+                profile.returned_cargo += last.halite;
+            }
+        }
+
+        for collision in collisions {
+            for &(owner, _) in collision.ships.iter().filter(|s| s.0 != game.my_id) {
+                let profile = self.profiles.entry(owner).or_default();
+                profile.collisions += 1;
+            }
+        }
+
+        for player in game.players.iter().filter(|p| p.id != game.my_id) {
+            let count = player.dropoff_ids.len();
+            let before = self.dropoffs_last.insert(player.id, count).unwrap_or(0);
+            if count > before {
+                let profile = self.profiles.entry(player.id).or_default();
+                profile.dropoff_turns.push(game.turn_number);
+            }
+        }
+
+        self.ships_last = game.ships.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scenario::Scenario;
+    use fixture::Frames;
+
+    /// Profile of player 1 after watching frames of a scenario
+    fn observe(frames: &[Game], collisions: &[Collision]) -> Profile {
+        let mut profiles = Profiles::new();
+        for (i, game) in frames.iter().enumerate() {
+            profiles.update(game, if i + 1 == frames.len() { collisions } else { &[] });
+        }
+        profiles.get(PlayerId(1)).cloned().unwrap()
+    }
+
+    #[test]
+    fn unobserved_player_has_base_rates() {
+        let profile = Profile::default();
+
+        assert_eq!(profile.adjacency_rate(), BASE_ADJACENCY);
+        assert_eq!(profile.collision_rate(), BASE_COLLISION);
+        assert_eq!(profile.return_threshold(), None);
+        assert_eq!(profile.risk_factor(), 1.0);
+    }
+
+    #[test]
+    fn moving_next_to_our_ships_raises_adjacency() {
+        let frame = |map: &str, turn: usize| {
+            let mut game = Scenario::new(map).game();
+            game.turn_number += turn;
+            game
+        };
+        let before = "
+            . . . . . .
+            . a . b . .
+            . S . . . S[1]
+        ";
+
+        let adjacent = observe(&[frame(before, 0), frame("
+            . . . . . .
+            . a b . . .
+            . S . . . S[1]
+        ", 1)], &[]);
+        assert_eq!(adjacent.opportunities, 1);
+        assert!(adjacent.adjacency_rate() > BASE_ADJACENCY);
+        assert!(adjacent.risk_factor() > 1.0);
+
+        let away = observe(&[frame(before, 0), frame("
+            . . . . . .
+            . a . . b .
+            . S . . . S[1]
+        ", 1)], &[]);
+        assert_eq!(away.opportunities, 1);
+        assert!(away.adjacency_rate() < BASE_ADJACENCY);
+        assert!(away.risk_factor() < 1.0);
+    }
+
+    #[test]
+    fn collisions_raise_collision_rate() {
+        let game = Scenario::new("
+            . . . . . .
+            . a . b . .
+            . S . . . S[1]
+        ").game();
+
+        let crash = Collision { turn: 1, pos: Position { x: 2, y: 1 }, ships: vec![(PlayerId(0), ShipId(0)), (PlayerId(1), ShipId(1))], dropped: 0 };
+        let profile = observe(&[game], &[crash]);
+
+        assert_eq!(profile.collisions, 1);
+        assert!(profile.collision_rate() > BASE_COLLISION);
+    }
+
+    #[test]
+    fn returns_set_the_threshold() {
+        let map = "
+            . . . . . .
+            . a . . b[600] .
+            . S . . . S[1]
+        ";
+        let before = Scenario::new(map).game();
+
+        // Enemy steps onto its shipyard and empties its cargo
+        let mut after = Scenario::new(map).game();
+        after.turn_number += 1;
+        {
+            let ship = after.ships.get_mut(&ShipId(1)).unwrap();
+            ship.position = Position { x: 5, y: 2 };
+            ship.halite = 0;
+        }
+
+        let profile = observe(&[before, after], &[]);
+        assert_eq!(profile.returns, 1);
+        assert_eq!(profile.return_threshold(), Some(600));
+    }
+
+    #[test]
+    fn enemy_dropoffs_record_their_turn() {
+        let before = Scenario::new("
+            . . . . . .
+            . a . . b .
+            . S . . . S[1]
+        ").game();
+
+        let mut after = Scenario::new("
+            . . . . . .
+            . a . . D[1] .
+            . S . . . S[1]
+        ").game();
+        after.turn_number += 10;
+        let built = after.turn_number;
+
+        let profile = observe(&[before, after], &[]);
+        assert_eq!(profile.dropoff_turns, vec![built]);
+        assert_eq!(profile.dropoff_interval(), Some(built));
+        assert!(!profile.dropoff_due(built + 1));
+        assert!(profile.dropoff_due(2 * built));
+    }
+}
@@ -0,0 +1,178 @@
+use hlt::*;
+use bot::Bot;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Directory of recorded engine input streams, each `NAME.input` paired with golden `NAME.commands`
+pub const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/golden");
+/// Budget replays run with, so the planner never cuts a turn short and results repeat on any machine
+const REPLAY_BUDGET: Duration = Duration::from_secs(3600);
+
+/// Commands the bot sent on one turn, sorted so emission order doesn't matter
+#[derive(PartialEq, Eq, Debug)]
+pub struct Turn {
+    pub turn: usize,
+    pub commands: Vec<String>,
+}
+
+/// Game reading a recorded stream and the bot to play it, before the first frame
+fn start(stream: String) -> (Game, Bot) {
+    let game = Game::from_reader(Cursor::new(stream));
+    let mut bot = Bot::new(&game);
+    bot.stats.set_budget(REPLAY_BUDGET);
+    (game, bot)
+}
+
+/// Play a recorded input stream through the full turn loop
+pub fn replay(stream: String) -> Vec<Turn> {
+    let (mut game, mut bot) = start(stream);
+
+    let mut turns = Vec::new();
+    bot.run(&mut game, |game, command_queue| {
+        let mut commands: Vec<String> = command_queue.iter().map(|command| command.0.clone()).collect();
+        commands.sort();
+        turns.push(Turn { turn: game.turn_number, commands });
+    });
+
+    turns
+}
+
+/// One line per turn, as `TURN: COMMAND, COMMAND, ...`
+pub fn format(turns: &[Turn]) -> String {
+    turns.iter()
+        .map(|turn| format!("{}: {}\n", turn.turn, turn.commands.join(", ")))
+        .collect()
+}
+
+pub fn parse(golden: &str) -> Vec<Turn> {
+    golden.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut parts = line.splitn(2, ':');
+            let turn = parts.next().unwrap().trim().parse().unwrap_or_else(|_| panic!("Bad golden line {:?}", line));
+            let commands = parts.next().unwrap_or("").split(',')
+                .map(|command| command.trim().to_string())
+                .filter(|command| !command.is_empty())
+                .collect();
+
+            Turn { turn, commands }
+        })
+        .collect()
+}
+
+/// Every turn on which the replayed commands differ from the golden ones
+pub fn diff(expected: &[Turn], actual: &[Turn]) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    for (expected, actual) in expected.iter().zip(actual) {
+        if expected != actual {
+            let missing: Vec<&str> = expected.commands.iter().filter(|c| !actual.commands.contains(c)).map(|c| c.as_str()).collect();
+            let extra: Vec<&str> = actual.commands.iter().filter(|c| !expected.commands.contains(c)).map(|c| c.as_str()).collect();
+            differences.push(format!("turn {}: missing {:?}, extra {:?}", expected.turn, missing, extra));
+        }
+    }
+
+    if expected.len() != actual.len() {
+        differences.push(format!("expected {} turns but replayed {}", expected.len(), actual.len()));
+    }
+
+    differences
+}
+
+/// Names of the recorded streams, sorted
+pub fn streams() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(GOLDEN_DIR)
+        .unwrap_or_else(|e| panic!("Couldn't read {}: {}", GOLDEN_DIR, e))
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "input"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .collect();
+
+    names.sort();
+    names
+}
+
+fn golden_path(name: &str, ext: &str) -> PathBuf {
+    Path::new(GOLDEN_DIR).join(format!("{}.{}", name, ext))
+}
+
+/// Replay a recorded stream and compare it against its golden commands
+pub fn check(name: &str) -> Vec<String> {
+    let stream = fs::read_to_string(golden_path(name, "input")).expect("Couldn't read input stream");
+    let golden = fs::read_to_string(golden_path(name, "commands")).unwrap_or_default();
+
+    diff(&parse(&golden), &replay(stream))
+}
+
+/// Game of a recorded stream read up to the frame of turn, with the bot that played every turn before it
+pub fn advance(name: &str, turn: usize) -> (Game, Bot) {
+    let stream = fs::read_to_string(golden_path(name, "input")).expect("Couldn't read input stream");
+    let (mut game, mut bot) = start(stream);
+
+    while game.update_frame() {
+        if game.turn_number >= turn {
+            return (game, bot);
+        }
+
+        bot.stats.start();
+        bot.turn(&game);
+    }
+
+    panic!("Stream {} ends before turn {}", name, turn);
+}
+
+/// Replay a recorded stream and overwrite its golden commands with the result
+pub fn refresh(name: &str) {
+    let stream = fs::read_to_string(golden_path(name, "input")).expect("Couldn't read input stream");
+    fs::write(golden_path(name, "commands"), format(&replay(stream))).expect("Couldn't write golden commands");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goldens_parse_back() {
+        let turns = vec![
+            Turn { turn: 1, commands: vec!["g".to_string()] },
+            Turn { turn: 2, commands: vec![] },
+            Turn { turn: 3, commands: vec!["c 4".to_string(), "m 0 n".to_string()] },
+        ];
+
+        assert_eq!(parse(&format(&turns)), turns);
+
+        let changed = vec![Turn { turn: 1, commands: vec![] }, Turn { turn: 2, commands: vec![] }];
+        let differences = diff(&turns, &changed);
+        assert_eq!(differences.len(), 2);
+        assert!(differences[0].starts_with("turn 1:"));
+    }
+
+    #[test]
+    fn advance_stops_at_the_turn() {
+        let name = &streams()[0];
+        let (game, bot) = advance(name, 5);
+
+        assert_eq!(game.turn_number, 5);
+        assert!(bot.paths().keys().all(|ship_id| game.ships.contains_key(ship_id)));
+    }
+
+    #[test]
+    fn replays_match_goldens() {
+        let names = streams();
+        assert!(!names.is_empty(), "No recorded streams in {}", GOLDEN_DIR);
+
+        let mut failures = Vec::new();
+        for name in names {
+            for difference in check(&name) {
+                failures.push(format!("{}: {}", name, difference));
+            }
+        }
+
+        assert!(failures.is_empty(), "Replays differ from goldens, refresh with `cargo run --release --bin golden -- --refresh` \
+                                      if intended:\n{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,24 @@
+/// Small deterministic generator so fixtures and rollouts repeat between runs
+#[derive(Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // Zero would stay zero forever
+        Rng(seed.max(1))
+    }
+
+    /// Uniform integer in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        // xorshift64*
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 33) as usize % n
+    }
+
+    /// True with probability p
+    pub fn chance(&mut self, p: f32) -> bool {
+        (self.below(1 << 24) as f32) < p * (1 << 24) as f32
+    }
+}
@@ -0,0 +1,231 @@
+use hlt::*;
+use state::{State, EnemyShip};
+use step::Event;
+use action::Action;
+use rng::Rng;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+/// Halite an enemy needs under it to consider staying to mine
+const ENEMY_MINE_THRESHOLD: usize = 50;
+/// Chance an enemy on a cell worth mining stays to mine it
+const ENEMY_MINE_CHANCE: f32 = 0.8;
+/// Cargo at which an enemy without a forecast target heads home
+const ENEMY_RETURN_RATIO: f32 = 0.75;
+
+/// Actions for each ship, one per turn from the rollout's starting state
+pub type Plan = HashMap<ShipId, VecDeque<Action>>;
+
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Score {
+    /// Mean halite deposited by our ships
+    pub returned: f32,
+    /// Mean number of our ships sunk
+    pub losses: f32,
+}
+
+impl Score {
+    /// Returned halite less the cost of replacing the ships lost
+    pub fn value(&self, ship_cost: usize) -> f32 {
+        self.returned - self.losses * ship_cost as f32
+    }
+}
+
+/// Scores plans by simulating many futures with enemies following a random policy
+pub struct Rollout {
+    pub samples: usize,
+    pub horizon: usize,
+    pub seed: u64,
+}
+
+impl Rollout {
+    pub fn new(samples: usize, horizon: usize) -> Rollout {
+        Rollout { samples, horizon, seed: 1 }
+    }
+
+    /// Score of each plan, every plan sampled against the same enemy draws
+    /// (common random numbers), so differences come from the plans rather than the dice
+    pub fn compare(&self, state: &State, plans: &[Plan]) -> Vec<Score> {
+        plans.iter().map(|plan| self.evaluate(state, plan)).collect()
+    }
+
+    pub fn evaluate(&self, state: &State, plan: &Plan) -> Score {
+        let mut total = Score::default();
+
+        for sample in 0..self.samples {
+            let (returned, losses) = self.sample(state, plan, sample);
+
+            total.returned += returned as f32;
+            total.losses += losses as f32;
+        }
+
+        let samples = self.samples.max(1) as f32;
+        Score { returned: total.returned / samples, losses: total.losses / samples }
+    }
+
+    /// Halite returned and ships lost in one simulated future
+    fn sample(&self, state: &State, plan: &Plan, sample: usize) -> (usize, usize) {
+        let mut state = state.clone();
+        let mut returned = 0;
+        let mut losses = 0;
+
+        for t in 0..self.horizon {
+            if state.turns_remaining() == 0 {
+                break;
+            }
+
+            // Ships without an action this turn stay still and mine
+            let mut commands = Vec::new();
+            for (&ship_id, actions) in plan {
+                if let Some(action) = actions.get(t).filter(|_| state.ships.contains_key(&ship_id)) {
+                    let command = if action.dropoff {
+                        Command::transform_ship_into_dropoff_site(ship_id)
+                    } else {
+                        Command::move_ship(ship_id, action.dir)
+                    };
+
+                    commands.push((state.my_id, command));
+                }
+            }
+
+            commands.extend(self.enemy_commands(&state, sample, t));
+
+            let (next, events) = state.step(&commands);
+            for event in events {
+                match event {
+                    Event::Deposit { owner, amount, .. } if owner == state.my_id => returned += amount,
+                    Event::Collision(collision) => {
+                        losses += collision.ships.iter().filter(|&&(owner, _)| owner == state.my_id).count();
+                    }
+                    _ => (),
+                }
+            }
+
+            state = next;
+        }
+
+        (returned, losses)
+    }
+
+    /// Moves of every enemy on turn t of a sample, sorted by ship id
+    fn enemy_commands(&self, state: &State, sample: usize, t: usize) -> Vec<(PlayerId, Command)> {
+        let mut enemies: Vec<(Position, EnemyShip)> = state.enemies.iter().cloned().collect();
+        enemies.sort_by_key(|&(_, enemy)| enemy.id.0);
+
+        enemies.into_iter()
+            .map(|(pos, enemy)| {
+                let dir = enemy_move(state, pos, enemy, &mut self.rng(sample, t, enemy.id));
+                (enemy.owner, Command::move_ship(enemy.id, dir))
+            })
+            .collect()
+    }
+
+    /// Draws for one enemy on one turn of a sample. Each gets its own stream, so a plan that
+    /// sinks an enemy or changes how many draws it takes leaves every other enemy's draws alone
+    fn rng(&self, sample: usize, t: usize, enemy: ShipId) -> Rng {
+        let key = (sample as u64) << 40 ^ (t as u64) << 20 ^ enemy.0 as u64;
+
+        // splitmix64 finalizer, so neighbouring keys start far apart
+        let mut z = self.seed ^ key.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Rng::new(z ^ (z >> 31))
+    }
+}
+
+/// Enemies head home when loaded, usually mine rich cells, and otherwise
+/// wander towards halite
+fn enemy_move(state: &State, pos: Position, enemy: EnemyShip, rng: &mut Rng) -> Direction {
+    let loaded = enemy.halite as f32 >= state.constants.max_halite as f32 * ENEMY_RETURN_RATIO;
+    let home = state.enemy_targets.get(&pos).cloned()
+        .or_else(|| if loaded { state.nearest_enemy_dropoff(enemy.owner, pos) } else { None });
+
+    if let Some(home) = home {
+        let directions = state.torus.directions(pos, home);
+        return if directions.is_empty() {
+            Direction::Still
+        } else {
+            directions[rng.below(directions.len())]
+        };
+    }
+
+    if state.halite(pos) >= ENEMY_MINE_THRESHOLD && rng.chance(ENEMY_MINE_CHANCE) {
+        return Direction::Still;
+    }
+
+    // Pick a neighbour with chance proportional to its halite
+    let weights: Vec<(Direction, usize)> = Direction::get_all_cardinals().into_iter()
+        .map(|dir| (dir, state.halite(state.normalize(pos.directional_offset(dir))) + 1))
+        .collect();
+
+    let mut pick = rng.below(weights.iter().map(|&(_, w)| w).sum());
+    for (dir, weight) in weights {
+        if pick < weight {
+            return dir;
+        }
+        pick -= weight;
+    }
+
+    Direction::Still
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixture::{Fixture, Frames};
+
+    fn plan(ship_id: ShipId, dirs: &[Direction]) -> Plan {
+        let actions = dirs.iter().map(|&dir| Action::new(ship_id, dir, false, false, false)).collect();
+        let mut plan = Plan::new();
+        plan.insert(ship_id, actions);
+        plan
+    }
+
+    #[test]
+    fn returning_plan_scores_its_cargo() {
+        // Shipyard at (4, 8) on a 16x16 map
+        let mut state = Fixture::new(16, 16, 2, 0).state();
+        state.update_hal(Position { x: 3, y: 8 }, 0);
+        state.ships.insert(ShipId(0), (Position { x: 3, y: 8 }, 500));
+        state.taken.set(Position { x: 3, y: 8 }, Some(ShipId(0)));
+
+        let rollout = Rollout::new(4, 3);
+        let scores = rollout.compare(&state, &[plan(ShipId(0), &[Direction::East]), Plan::new()]);
+
+        assert_eq!(scores[0].returned, 500.0);
+        assert_eq!(scores[0].losses, 0.0);
+        assert_eq!(scores[1].returned, 0.0);
+    }
+
+    #[test]
+    fn samples_repeat_between_runs() {
+        let state = Fixture::new(32, 32, 2, 10).state();
+        assert!(!state.enemies.is_empty());
+
+        let rollout = Rollout::new(8, 10);
+        let first = rollout.evaluate(&state, &Plan::new());
+        let second = rollout.evaluate(&state, &Plan::new());
+
+        assert_eq!(first.returned, second.returned);
+        assert_eq!(first.losses, second.losses);
+    }
+
+    #[test]
+    fn enemy_draws_ignore_other_enemies() {
+        let mut state = Fixture::new(32, 32, 2, 10).state();
+        let rollout = Rollout::new(1, 1);
+        let moves = |state: &State, t: usize| -> Vec<String> {
+            rollout.enemy_commands(state, 0, t).into_iter().map(|(_, command)| command.0).collect()
+        };
+
+        for t in 0..5 {
+            // Sinking the first enemy leaves the others drawing the same moves
+            let &(pos, _) = state.enemies.iter().min_by_key(|&&(_, enemy)| enemy.id.0).unwrap();
+            let mut fewer = state.clone();
+            fewer.enemies.remove(&pos);
+
+            assert_eq!(moves(&fewer, t), moves(&state, t)[1..].to_vec());
+            state.turn += 1;
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use hlt::*;
+use action::{Action, MergedAction};
+use timeline::Timeline;
+use profile::Profiles;
+use stats::Stats;
+use fixture::{Frames, CONSTANTS};
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+/// Turn a scenario is played from unless told otherwise
+const TURN: usize = 1;
+/// Halite each player has banked unless told otherwise
+const BANK: usize = 5000;
+
+/// One cell of a scenario map
+#[derive(Copy, Clone)]
+enum Cell {
+    Halite(usize),
+    Shipyard(PlayerId),
+    Dropoff(PlayerId),
+    Ship(PlayerId, usize),
+}
+
+/// Game built from a compact ASCII map, one character per cell, for testing planner decisions.
+///
+/// `.` is an empty cell and `0`-`9` a cell with that many hundred halite. `S` is a shipyard and
+/// `D` a dropoff, ours unless followed by the owner's player id in brackets, as in `S[1]`. `a` is
+/// one of our ships and `b`, `c` and `d` ships of players 1 to 3, empty unless followed by their
+/// cargo, as in `a[900]`. Ships and dropoffs are numbered in reading order; whitespace is ignored.
+pub struct Scenario {
+    pub map: String,
+    pub turn: usize,
+    pub bank: usize,
+}
+
+impl Scenario {
+    pub fn new(map: &str) -> Scenario {
+        Scenario { map: map.to_string(), turn: TURN, bank: BANK }
+    }
+
+    /// Timeline planned from the game and the paths ships are already following
+    pub fn timeline(game: &Game, paths: &mut HashMap<ShipId, VecDeque<Action>>) -> Timeline {
+        Timeline::from(game, Vec::new(), paths, &Profiles::new(), &HashMap::new(), &Stats::new())
+    }
+
+    fn parse(&self) -> Vec<Vec<Cell>> {
+        let rows: Vec<Vec<Cell>> = self.map.lines()
+            .map(Scenario::parse_row)
+            .filter(|row| !row.is_empty())
+            .collect();
+
+        assert!(!rows.is_empty(), "Scenario map is empty");
+        for row in &rows {
+            assert_eq!(row.len(), rows[0].len(), "Scenario map rows differ in length");
+        }
+
+        rows
+    }
+
+    fn parse_row(line: &str) -> Vec<Cell> {
+        let mut chars = line.chars().filter(|c| !c.is_whitespace()).peekable();
+        let mut row = Vec::new();
+
+        while let Some(c) = chars.next() {
+            // Optional bracketed number after the cell
+            let annotation = if chars.peek() == Some(&'[') {
+                let number: String = chars.by_ref().skip(1).take_while(|&c| c != ']').collect();
+                Some(number.parse::<usize>().unwrap_or_else(|_| panic!("Bad annotation [{}] in scenario", number)))
+            } else {
+                None
+            };
+
+            let cell = match c {
+                '.' => Cell::Halite(0),
+                '0'..='9' => Cell::Halite(c.to_digit(10).unwrap() as usize * 100),
+                'S' => Cell::Shipyard(PlayerId(annotation.unwrap_or(0))),
+                'D' => Cell::Dropoff(PlayerId(annotation.unwrap_or(0))),
+                'a'..='d' => Cell::Ship(PlayerId(c as usize - 'a' as usize), annotation.unwrap_or(0)),
+                c => panic!("Unknown cell '{}' in scenario", c),
+            };
+
+            row.push(cell);
+        }
+
+        row
+    }
+}
+
+impl Frames for Scenario {
+    fn frames(&self) -> String {
+        let rows = self.parse();
+        let height = rows.len();
+        let width = rows[0].len();
+
+        let cells: Vec<(Position, Cell)> = rows.iter().enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &cell)| (Position { x: x as i32, y: y as i32 }, cell)))
+            .collect();
+
+        let mut shipyards: Vec<Option<Position>> = Vec::new();
+        for &(pos, cell) in &cells {
+            let owner = match cell {
+                Cell::Shipyard(owner) | Cell::Dropoff(owner) | Cell::Ship(owner, _) => owner.0,
+                Cell::Halite(_) => continue,
+            };
+
+            if owner >= shipyards.len() {
+                shipyards.resize(owner + 1, None);
+            }
+
+            if let Cell::Shipyard(_) = cell {
+                assert!(shipyards[owner].is_none(), "Player {} has two shipyards", owner);
+                shipyards[owner] = Some(pos);
+            }
+        }
+
+        let mut frames = String::from(CONSTANTS);
+
+        frames.push_str(&format!("{} 0\n", shipyards.len()));
+        for (id, shipyard) in shipyards.iter().enumerate() {
+            let pos = shipyard.unwrap_or_else(|| panic!("Player {} has no shipyard", id));
+            frames.push_str(&format!("{} {} {}\n", id, pos.x, pos.y));
+        }
+
+        frames.push_str(&format!("{} {}\n", width, height));
+        for row in &rows {
+            let halite: Vec<String> = row.iter()
+                .map(|&cell| if let Cell::Halite(halite) = cell { halite } else { 0 })
+                .map(|halite| halite.to_string())
+                .collect();
+            frames.push_str(&halite.join(" "));
+            frames.push('\n');
+        }
+
+        frames.push_str(&format!("{}\n", self.turn));
+
+        let (mut ship_id, mut dropoff_id) = (0, 0);
+        let mut ships = vec![Vec::new(); shipyards.len()];
+        let mut dropoffs = vec![Vec::new(); shipyards.len()];
+        for &(pos, cell) in &cells {
+            match cell {
+                Cell::Ship(owner, cargo) => {
+                    ships[owner.0].push(format!("{} {} {} {}\n", ship_id, pos.x, pos.y, cargo));
+                    ship_id += 1;
+                }
+                Cell::Dropoff(owner) => {
+                    dropoffs[owner.0].push(format!("{} {} {}\n", dropoff_id, pos.x, pos.y));
+                    dropoff_id += 1;
+                }
+                _ => (),
+            }
+        }
+
+        for id in 0..shipyards.len() {
+            frames.push_str(&format!("{} {} {} {}\n", id, ships[id].len(), dropoffs[id].len(), self.bank));
+            frames.push_str(&ships[id].concat());
+            frames.push_str(&dropoffs[id].concat());
+        }
+
+        frames.push_str("0\n");
+        frames
+    }
+}
+
+/// Direction of a move command sent to a ship, if any
+pub fn move_of(commands: &[Command], ship_id: ShipId) -> Option<Direction> {
+    commands.iter()
+        .map(|command| command.0.split_whitespace().collect::<Vec<_>>())
+        .filter(|words| words.len() == 3 && words[0] == "m" && words[1] == ship_id.0.to_string())
+        .filter_map(|words| words[2].chars().next().and_then(Direction::from_char_encoding))
+        .next()
+}
+
+/// Panic unless the ship was commanded to move in dir
+pub fn assert_move(commands: &[Command], ship_id: ShipId, dir: Direction) {
+    let commands_sent: Vec<&str> = commands.iter().map(|command| command.0.as_str()).collect();
+    assert_eq!(move_of(commands, ship_id), Some(dir), "Ship {} moves, commands {:?}", ship_id.0, commands_sent);
+}
+
+/// Panic unless a searched path starts by moving in dir
+pub fn assert_path_starts(game: &Game, path: &[MergedAction], dir: Direction) {
+    assert!(path.len() >= 2, "Path has no moves");
+
+    let torus = game.map.torus;
+    let first = torus.directions(path[0].pos, path[1].pos).first().cloned().unwrap_or(Direction::Still);
+    let steps: Vec<(i32, i32)> = path.iter().map(|action| (action.pos.x, action.pos.y)).collect();
+
+    assert_eq!(first, dir, "Path {:?}", steps);
+}
+
+/// Panic unless some ship's path turns it into a dropoff at pos within the given number of turns
+pub fn assert_dropoff_within(game: &Game, paths: &HashMap<ShipId, VecDeque<Action>>, pos: Position, turns: usize) {
+    let mut planned = Vec::new();
+
+    for (ship_id, path) in paths {
+        let mut ship_pos = game.ships[ship_id].position;
+        for (t, action) in path.iter().enumerate() {
+            if action.dropoff {
+                planned.push(((ship_pos.x, ship_pos.y), t));
+                break;
+            }
+
+            ship_pos = game.map.normalize(&ship_pos.directional_offset(action.dir));
+        }
+    }
+
+    let found = planned.iter().any(|&((x, y), t)| Position { x, y } == pos && t <= turns);
+    assert!(found, "No dropoff at ({}, {}) within {} turns, planned {:?}", pos.x, pos.y, turns, planned);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_parses_into_game() {
+        let game = Scenario::new("
+            . 1 9 .
+            S a[500] . b[20]
+            . D . S[1]
+        ").game();
+
+        assert_eq!((game.map.width, game.map.height), (4, 3));
+        assert_eq!(game.map.at_position(&Position { x: 2, y: 0 }).halite, 900);
+        assert_eq!(game.players.len(), 2);
+        assert_eq!(game.players[1].shipyard.position, Position { x: 3, y: 2 });
+
+        let ours = &game.ships[&ShipId(0)];
+        assert_eq!((ours.owner, ours.position, ours.halite), (PlayerId(0), Position { x: 1, y: 1 }, 500));
+
+        let enemy = &game.ships[&ShipId(1)];
+        assert_eq!((enemy.owner, enemy.position, enemy.halite), (PlayerId(1), Position { x: 3, y: 1 }, 20));
+
+        assert_eq!(game.dropoffs[&DropoffId(0)].position, Position { x: 1, y: 2 });
+        assert_eq!(game.players[0].halite, BANK);
+    }
+
+    #[test]
+    fn ship_letters_name_their_owner() {
+        let game = Scenario::new("
+            S a . b . S[1]
+            . . . . . .
+            S[2] c . d[300] . S[3]
+        ").game();
+
+        assert_eq!(game.players.len(), 4);
+        let owners: Vec<(usize, usize)> = (0..4).map(|id| (game.ships[&ShipId(id)].owner.0, game.ships[&ShipId(id)].halite)).collect();
+        assert_eq!(owners, vec![(0, 0), (1, 0), (2, 0), (3, 300)]);
+    }
+
+    #[test]
+    fn commands_are_read_back() {
+        let commands = vec![Command::spawn_ship(), Command::move_ship(ShipId(12), Direction::East)];
+
+        assert_eq!(move_of(&commands, ShipId(12)), Some(Direction::East));
+        assert_eq!(move_of(&commands, ShipId(1)), None);
+        assert_move(&commands, ShipId(12), Direction::East);
+    }
+}
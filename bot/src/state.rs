@@ -1,71 +1,92 @@
 use im_rc as im;
 use hlt::*;
 use action::{Action, MergedAction};
+use std::collections::HashMap;
+use profile::Profiles;
+use collision::{Spill, SPILL_PRIORITY};
+use grid::Grid;
+
+/// Confidence kept per turn of forecasting enemy positions
+const INSPIRATION_DECAY: f32 = 0.95;
+
+#[derive(Copy, Clone)]
+pub struct EnemyShip {
+    pub owner: PlayerId,
+    pub id: ShipId,
+    pub halite: usize,
+}
 
 pub struct State {
-    pub map: im::HashMap<Position, usize>,
+    pub map: Grid<usize>,
     pub ships: im::HashMap<ShipId, (Position, usize)>,
-    pub taken: im::HashMap<Position, ShipId>,
-    pub enemies: im::HashMap<Position, usize>,
-    pub inspired: im::HashSet<Position>,
+    pub taken: Grid<Option<ShipId>>,
+    pub enemies: im::HashMap<Position, EnemyShip>,
+    pub enemy_targets: im::HashMap<Position, Position>,
+    pub risk_factors: im::HashMap<PlayerId, f32>,
+    pub inspired: Grid<Option<f32>>,
+    pub spills: im::HashMap<Position, usize>,
+    pub lanes: im::HashMap<Position, Lane>,
+    pub arrivals: im::HashMap<Position, usize>,
     pub dropoffs: im::HashSet<Position>,
-    pub enemy_dropoffs: im::HashSet<Position>,
+    pub enemy_dropoffs: im::HashMap<Position, PlayerId>,
+    pub shipyards: im::HashMap<PlayerId, Position>,
+    pub banks: im::HashMap<PlayerId, usize>,
+    pub my_id: PlayerId,
+    pub next_ship_id: usize,
     pub num_players: usize,
     pub halite: usize,
     pub width: usize,
     pub height: usize,
+    pub torus: Torus,
     pub turn: usize,
     pub start: usize,
     pub constants: Constants,
 }
 
 impl State {
-    pub fn from(game: &Game) -> State {
-        let mut map = im::HashMap::new();
-        for cell in game.map.iter() {
-            map.insert(cell.position, cell.halite);
-        }
+    pub fn from(game: &Game, profiles: &Profiles, spills: &HashMap<Position, Spill>) -> State {
+        let map = Grid::from_fn(game.map.torus, |pos| game.map.at_position(&pos).halite);
 
         let ships = im::HashMap::new();
-        let taken = im::HashMap::new();
+        let taken = Grid::new(game.map.torus, None);
         let me = game.players.iter().find(|p| p.id == game.my_id).unwrap();
         let halite = me.halite;
 
         let mut enemies = im::HashMap::new();
         for ship in game.ships.values() {
             if ship.owner != game.my_id {
-                enemies.insert(ship.position, ship.halite);
+                enemies.insert(ship.position, EnemyShip { owner: ship.owner, id: ship.id, halite: ship.halite });
             }
         }
 
         let num_players = game.players.len();
 
-        let mut inspired = im::HashSet::new();
-        for pos in game.map.iter().map(|cell| cell.position) {
-            let mut c = 0;
-            for &ship_pos in enemies.keys() {
-                if game.map.calculate_distance(&pos, &ship_pos) <= game.constants.inspiration_radius {
-                    c += 1;
-                }
-
-                if c == game.constants.inspiration_ship_count {
-                    inspired.insert(pos);
-                    break
-                }
-            }
-        }
-
         let dropoffs: im::HashSet<Position> = std::iter::once(me.shipyard.position)
             .chain(me.dropoff_ids.iter().map(|id| game.dropoffs[id].position))
             .collect();
 
-        let mut enemy_dropoffs = im::HashSet::new();
+        let shipyards = game.players.iter().map(|p| (p.id, p.shipyard.position)).collect();
+        let next_ship_id = game.ships.keys().map(|id| id.0 + 1).max().unwrap_or(0);
+
+        let mut enemy_dropoffs = im::HashMap::new();
+        let mut risk_factors = im::HashMap::new();
+        let mut return_thresholds = HashMap::new();
+        let mut banks = im::HashMap::new();
         for player in &game.players {
             if player.id != me.id {
-                enemy_dropoffs.insert(player.shipyard.position);
+                banks.insert(player.id, player.halite);
+
+                enemy_dropoffs.insert(player.shipyard.position, player.id);
                 for dropoff_id in &player.dropoff_ids {
-                    enemy_dropoffs.insert(game.dropoffs[dropoff_id].position);
+                    enemy_dropoffs.insert(game.dropoffs[dropoff_id].position, player.id);
                 }
+
+                let threshold = profiles.get(player.id)
+                    .and_then(|profile| profile.return_threshold())
+                    .unwrap_or(game.constants.max_halite * 3 / 4);
+
+                risk_factors.insert(player.id, profiles.risk_factor(player.id));
+                return_thresholds.insert(player.id, threshold);
             }
         }
 
@@ -76,35 +97,129 @@ impl State {
 
         let constants = game.constants.clone();
         
-        State {
+        let mut state = State {
             map,
             ships,
             taken,
             enemies,
-            inspired,
+            enemy_targets: im::HashMap::new(),
+            risk_factors,
+            inspired: Grid::new(game.map.torus, None),
+            spills: spills.iter().map(|(&pos, spill)| (pos, spill.expires)).collect(),
+            lanes: im::HashMap::new(),
+            arrivals: im::HashMap::new(),
             dropoffs,
             enemy_dropoffs,
+            shipyards,
+            banks,
+            my_id: me.id,
+            next_ship_id,
             num_players,
             halite,
             width,
             height,
+            torus: game.map.torus,
             turn,
             start,
             constants,
+        };
+
+        // Enemies carrying their usual return cargo are expected to head home
+        for &(pos, enemy) in state.enemies.clone().iter() {
+            if enemy.halite >= return_thresholds[&enemy.owner] {
+                if let Some(target) = state.nearest_enemy_dropoff(enemy.owner, pos) {
+                    state.enemy_targets.insert(pos, target);
+                }
+            }
         }
+
+        state.forecast_inspiration();
+        state
     }
 
-    pub fn calculate_distance(&self, source: Position, target: Position) -> usize {
-        let normalized_source = self.normalize(source);
-        let normalized_target = self.normalize(target);
+    /// Ships of player within radius of pos
+    pub fn enemy_ships_within(&self, player_id: PlayerId, pos: Position, radius: usize) -> Vec<(Position, EnemyShip)> {
+        self.enemies.iter()
+            .filter(|&&(p, enemy)| enemy.owner == player_id && self.calculate_distance(pos, p) <= radius)
+            .cloned()
+            .collect()
+    }
 
-        let dx = (normalized_source.x - normalized_target.x).abs() as usize;
-        let dy = (normalized_source.y - normalized_target.y).abs() as usize;
+    /// Nearest shipyard or dropoff of player to pos
+    pub fn nearest_enemy_dropoff(&self, player_id: PlayerId, pos: Position) -> Option<Position> {
+        self.enemy_dropoffs.iter()
+            .filter(|&&(_, owner)| owner == player_id)
+            .map(|&(p, _)| p)
+            .min_by_key(|&p| self.calculate_distance(pos, p))
+    }
+
+    /// Opponents which own a ship or structure
+    pub fn enemy_players(&self) -> Vec<PlayerId> {
+        let mut players: Vec<PlayerId> = self.enemy_dropoffs.values()
+            .cloned()
+            .chain(self.enemies.values().map(|enemy| enemy.owner))
+            .collect();
+
+        players.sort_by_key(|p| p.0);
+        players.dedup();
+        players
+    }
 
-        let toroidal_dx = dx.min(self.width - dx);
-        let toroidal_dy = dy.min(self.height - dy);
+    /// Predicted position of the enemy starting at pos after t turns
+    pub fn forecast_enemy(&self, pos: Position, t: usize) -> Position {
+        match self.enemy_targets.get(&pos) {
+            Some(&target) => {
+                let (dx, dy) = self.offset(pos, target);
+                let sx = dx.abs().min(t as i32);
+                let sy = dy.abs().min(t as i32 - sx);
 
-        toroidal_dx + toroidal_dy
+                self.normalize(Position { x: pos.x + sx * dx.signum(), y: pos.y + sy * dy.signum() })
+            }
+            None => pos,
+        }
+    }
+
+    /// Recompute inspired cells from the enemy positions forecast for this timestep
+    pub fn forecast_inspiration(&mut self) {
+        if !self.constants.inspiration_enabled {
+            return;
+        }
+
+        let t = self.turn - self.start;
+        let radius = self.constants.inspiration_radius;
+        let mut counts = vec![0; self.torus.size()];
+
+        // Count enemies within range of each cell by stamping a ball around each enemy
+        for &pos in self.enemies.keys() {
+            let center = self.forecast_enemy(pos, t);
+            for p in self.torus.ball(center, radius) {
+                counts[self.torus.index(p)] += 1;
+            }
+        }
+
+        let confidence = INSPIRATION_DECAY.powi(t as i32);
+        let ship_count = self.constants.inspiration_ship_count;
+        let torus = self.torus;
+        self.inspired = Grid::from_fn(torus, |pos| {
+            Some(confidence).filter(|_| counts[torus.index(pos)] >= ship_count)
+        });
+    }
+
+    /// Mined halite including the expected inspiration bonus at pos
+    pub fn inspire(&self, pos: Position, mined: usize) -> usize {
+        match self.inspired[pos] {
+            Some(confidence) => mined + (mined as f64 * self.constants.inspired_bonus_multiplier * confidence as f64) as usize,
+            None => mined,
+        }
+    }
+
+    /// Shortest signed offset from source to target
+    pub fn offset(&self, source: Position, target: Position) -> (i32, i32) {
+        self.torus.offset(source, target)
+    }
+
+    pub fn calculate_distance(&self, source: Position, target: Position) -> usize {
+        self.torus.distance(source, target)
     }
 
     pub fn enemy_value(&self, pos: Position) -> Option<usize> {
@@ -113,14 +228,14 @@ impl State {
 
         if self.enemies.contains_key(&pos) {
             c += 1;
-            total += self.enemies[&pos];
+            total += self.enemies[&pos].halite;
         }
 
         for dir in Direction::get_all_cardinals() {
             let new_pos = self.normalize(pos.directional_offset(dir));
             if self.enemies.contains_key(&new_pos) {
                 c += 1;
-                total += self.enemies[&new_pos];
+                total += self.enemies[&new_pos].halite;
             }
         }
 
@@ -131,10 +246,19 @@ impl State {
         }
     }
 
+    /// Risk factor of the most dangerous enemy at or next to pos
+    pub fn risk_factor(&self, pos: Position) -> f32 {
+        std::iter::once(pos)
+            .chain(Direction::get_all_cardinals().into_iter().map(|dir| self.normalize(pos.directional_offset(dir))))
+            .filter_map(|p| self.enemies.get(&p))
+            .filter_map(|enemy| self.risk_factors.get(&enemy.owner))
+            .fold(0.0, |max, &factor| max.max(factor))
+    }
+
     pub fn friendly_presence(&self, pos: Position, ship_id: ShipId, value: usize) -> Option<usize> {
         let mut count = 0;
         let mut cargo = 0;
-        for &(friendly_pos, friendly_id) in self.taken.iter() {
+        for (friendly_pos, &friendly_id) in self.taken.iter().filter_map(|(p, id)| id.as_ref().map(|id| (p, id))) {
             let dist_to = self.calculate_distance(pos, friendly_pos);
             let hal = self.ship(friendly_id).1;
             if dist_to <= 3 && ship_id != friendly_id {
@@ -151,7 +275,7 @@ impl State {
 
     pub fn friendly_distance(&self, pos: Position) -> f32 {
         let mut dist = 0f32;
-        for &friendly_pos in self.taken.keys() {
+        for (friendly_pos, _) in self.taken.iter().filter(|(_, id)| id.is_some()) {
             let dist_to = self.calculate_distance(pos, friendly_pos);
             dist += 1.0 / (dist_to as f32 + 1.0)
         }
@@ -172,19 +296,15 @@ impl State {
     }
 
     pub fn normalize(&self, position: Position) -> Position {
-        let width = self.width as i32;
-        let height = self.height as i32;
-        let x = ((position.x % width) + width) % width;
-        let y = ((position.y % height) + height) % height;
-        Position { x, y }
+        self.torus.normalize(position)
     }
 
     pub fn halite(&self, pos: Position) -> usize {
-        self.map[&pos]
+        self.map[pos]
     }
 
     pub fn update_hal(&mut self, pos: Position, hal: usize) {
-        self.map.insert(pos, hal).expect(&format!("No cell at pos ({}, {})", pos.x, pos.y));
+        self.map.set(pos, hal);
     }
 
     pub fn ship(&self, ship_id: ShipId) -> (Position, usize) {
@@ -193,56 +313,45 @@ impl State {
 
     pub fn update_ship(&mut self, ship_id: ShipId, pos: Position, hal: usize) {
         let ship = self.ships.get_mut(&ship_id).expect(&format!("No ship with id {}", ship_id.0));
-        if self.taken.get(&ship.0) == Some(&ship_id) {
-            self.taken.remove(&ship.0);
+        if self.taken[ship.0] == Some(ship_id) {
+            self.taken.set(ship.0, None);
         }
-        self.taken.insert(pos, ship_id);
+        self.taken.set(pos, Some(ship_id));
         *ship = (pos, hal);
     }
 
+    /// First direction along a shortest route from source to destination
     pub fn get_dir(&self, source: Position, destination: Position) -> Direction {
-        let normalized_source = self.normalize(source);
-        let normalized_destination = self.normalize(destination);
+        self.torus.directions(source, destination).first().cloned().unwrap_or(Direction::Still)
+    }
 
-        if normalized_source == normalized_destination {
-            return Direction::Still;
+    /// Whether lanes around our dropoffs allow a move arriving this timestep
+    pub fn lane_allows(&self, from: Position, to: Position) -> bool {
+        if self.end_game() {
+            return true;
         }
 
-        let dx = (normalized_source.x - normalized_destination.x).abs() as usize;
-        let dy = (normalized_source.y - normalized_destination.y).abs() as usize;
+        let from = (from, self.lanes.get(&from).cloned());
+        let to = (to, self.lanes.get(&to).cloned());
 
-        let wrapped_dx = self.width - dx;
-        let wrapped_dy = self.height - dy;
-
-        if normalized_source.x < normalized_destination.x {
-            if dx > wrapped_dx { 
-                return Direction::West 
-            } else { 
-                return Direction::East 
-            }
-        } else if normalized_source.x > normalized_destination.x {
-            if dx < wrapped_dx { 
-                return Direction::West 
-            } else {
-                return Direction::East 
-            }
+        if Lane::forbids(from, to) {
+            return false;
         }
 
-        if normalized_source.y < normalized_destination.y {
-            if dy > wrapped_dy { 
-                return Direction::North 
-            } else {
-                return Direction::South 
-            }
-        } else if normalized_source.y > normalized_destination.y {
-            if dy < wrapped_dy {
-                return Direction::North 
-            } else {
-                return Direction::South 
-            }
+        match Lane::arrival(from, to) {
+            Some(dropoff) => self.arrivals.get(&dropoff).cloned().unwrap_or(0) < MAX_ARRIVALS,
+            None => true,
         }
+    }
+
+    /// Count a move arriving this timestep against the dropoff it approaches
+    pub fn record_move(&mut self, from: Position, to: Position) {
+        let from = (from, self.lanes.get(&from).cloned());
+        let to = (to, self.lanes.get(&to).cloned());
 
-        panic!("This should never happen");
+        if let Some(dropoff) = Lane::arrival(from, to) {
+            *self.arrivals.entry(dropoff).or_insert(0) += 1;
+        }
     }
 
     pub fn move_ship(&mut self, ship_id: ShipId, dir: Direction) {
@@ -260,18 +369,21 @@ impl State {
             after_move
         };
 
+        self.record_move(ship.0, new_pos);
         self.update_ship(ship_id, new_pos, new_hal);
     }
 
     pub fn make_dropoff(&mut self, ship_id: ShipId) {
         let (pos, hal) = self.ships.remove(&ship_id).expect("Cannot convert ship to dropoff");
         let hal_at_pos = self.halite(pos);
-        let cost = self.constants.dropoff_cost - hal - hal_at_pos;
+        let cost = self.constants.dropoff_cost.saturating_sub(hal + hal_at_pos);
 
+        // Halite on the cell goes towards the cost of the dropoff
         self.halite -= cost;
+        self.update_hal(pos, 0);
         self.dropoffs.insert(pos);
-        if self.taken.contains_key(&pos) && self.taken[&pos] == ship_id {
-            self.taken.remove(&pos);
+        if self.taken[pos] == Some(ship_id) {
+            self.taken.set(pos, None);
         }
     }
 
@@ -284,12 +396,7 @@ impl State {
 
         self.update_hal(pos, hal - mined);
 
-        let mined = if self.inspired.contains(&pos) {
-            3 * mined
-        } else {
-            mined
-        };
-        let mined = mined.min(cap);
+        let mined = self.inspire(pos, mined).min(cap);
 
         self.update_ship(ship_id, pos, ship.1 + mined);
     }
@@ -303,15 +410,15 @@ impl State {
     }
 
     pub fn apply_merged_mut(&mut self, merged: &MergedAction) {
-        if !self.taken.contains_key(&merged.pos) {
-            self.taken.insert(merged.pos, merged.ship_id);
+        if self.taken[merged.pos].is_none() {
+            self.taken.set(merged.pos, Some(merged.ship_id));
         } 
         self.ships.insert(merged.ship_id, (merged.pos, merged.halite));
 
         self.halite += merged.returned;
 
         for &(pos, hal) in &merged.mined {
-            self.map[&pos] = hal;
+            self.map.set(pos, hal);
         }
     }
 
@@ -356,15 +463,20 @@ impl State {
         let position = merged.pos;
         let halite = merged.halite;
 
-        let cost = state.map[&position] / state.constants.move_cost_ratio;
+        let cost = state.halite(position) / state.constants.move_cost_ratio;
         let mut actions = Vec::new();
 
         if state.turns_remaining() > 0 {
             if halite >= cost {
                 for dir in Direction::get_all_cardinals() {
                     let new_pos = state.normalize(position.directional_offset(dir));
-                    let inspired = state.inspired.contains(&new_pos);
-                    if state.taken.contains_key(&new_pos) {
+                    let inspired = state.inspired[new_pos].is_some();
+
+                    if !state.lane_allows(position, new_pos) {
+                        continue;
+                    }
+
+                    if state.taken[new_pos].is_some() {
                         if state.dropoffs.contains(&new_pos) && self.end_game() {
                             let mut action = merged.clone();
 
@@ -373,6 +485,7 @@ impl State {
                             action.halite = 0;
                             action.inspired = inspired;
                             action.cost += 2 * state.constants.ship_cost as i32;
+                            action.breakdown.end_game += 2 * state.constants.ship_cost as i32;
 
                             actions.push(action);
                         }
@@ -391,18 +504,23 @@ impl State {
                         action.halite = new_hal;
                         action.inspired = inspired;
                         action.cost += cost as i32;
+                        action.breakdown.moves += cost as i32;
 
                         if self.enemy_value(new_pos).is_some() {
+                            let factor = self.risk_factor(new_pos);
                             action.risk = true;
-                            action.cost += if state.enemies.contains_key(&new_pos) {
+                            let penalty = if state.enemies.contains_key(&new_pos) {
                                 1000
                             } else {
                                 if state.num_players == 2 {
-                                    action.halite as i32 / 5
+                                    (action.halite as f32 / 5.0 * factor) as i32
                                 } else {
-                                    1000
+                                    (1000.0 * factor) as i32
                                 }
-                            }
+                            };
+
+                            action.cost += penalty;
+                            action.breakdown.risk += penalty;
                         }
 
                         actions.push(action);
@@ -410,7 +528,7 @@ impl State {
                 }
             } 
 
-            if state.taken[&position] == ship_id {
+            if state.taken[position] == Some(ship_id) {
                 let mut action = merged.clone();
 
                 let hal = state.halite(position);
@@ -420,14 +538,8 @@ impl State {
 
                 let hal_after = hal - mined;
 
-                let mined = if state.inspired.contains(&position) {
-                    action.inspired = true;
-                    3 * mined
-                } else {
-                    action.inspired = false;
-                    mined
-                };
-                let mined = mined.min(cap);
+                action.inspired = state.inspired[position].is_some();
+                let mined = state.inspire(position, mined).min(cap);
 
                 action.halite += mined;
 
@@ -439,6 +551,13 @@ impl State {
 
                 if !already_mined {
                     action.cost -= mined as i32;
+                    action.breakdown.mining -= mined as i32;
+
+                    // Collect dropped halite before someone else does
+                    if state.spills.get(&position).map(|&expires| state.turn < expires).unwrap_or(false) {
+                        action.cost -= (mined * SPILL_PRIORITY) as i32;
+                        action.breakdown.mining -= (mined * SPILL_PRIORITY) as i32;
+                    }
                 }
 
                 actions.push(action);
@@ -450,18 +569,20 @@ impl State {
 
     pub fn add_ship(&mut self, ship: &Ship) {
         self.ships.insert(ship.id, (ship.position, ship.halite));
-        self.taken.insert(ship.position, ship.id);
+        self.taken.set(ship.position, Some(ship.id));
     }
 
     pub fn rm_ship(&mut self, ship_id: ShipId) {
         if let Some((position, _)) = self.ships.remove(&ship_id) {
-            self.taken.remove(&position);
+            self.taken.set(position, None);
         }
     }
 
     pub fn next(&self) -> State {
         let mut state = self.clone();
         state.turn += 1;
+        state.arrivals.clear();
+        state.forecast_inspiration();
 
         state
     }
@@ -470,7 +591,7 @@ impl State {
         let (pos, hal) = self.ship(action.ship_id);
         let new_pos = self.normalize(pos.directional_offset(action.dir));
 
-        if action.dir == Direction::Still && action.inspired && !self.inspired.contains(&new_pos) {
+        if action.dir == Direction::Still && action.inspired && self.inspired[new_pos].is_none() {
             return false;
         }
 
@@ -479,13 +600,13 @@ impl State {
         }
 
         if action.dropoff {
-            return self.halite + hal + self.halite(pos) >= self.constants.dropoff_cost && !self.enemy_dropoffs.contains(&pos) && !self.dropoffs.contains(&pos)
+            return self.halite + hal + self.halite(pos) >= self.constants.dropoff_cost && !self.enemy_dropoffs.contains_key(&pos) && !self.dropoffs.contains(&pos)
         }
 
         match action.dir {
             Direction::Still => true,
             _ => {
-                let cost = self.map[&pos] / self.constants.move_cost_ratio;
+                let cost = self.halite(pos) / self.constants.move_cost_ratio;
                 hal >= cost
             }
         }
@@ -510,9 +631,16 @@ impl Clone for State {
             ships: self.ships.clone(),
             taken: self.taken.clone(),
             enemies: self.enemies.clone(),
+            enemy_targets: self.enemy_targets.clone(),
+            risk_factors: self.risk_factors.clone(),
             inspired: self.inspired.clone(),
+            spills: self.spills.clone(),
+            lanes: self.lanes.clone(),
+            arrivals: self.arrivals.clone(),
             dropoffs: self.dropoffs.clone(),
             enemy_dropoffs: self.enemy_dropoffs.clone(),
+            shipyards: self.shipyards.clone(),
+            banks: self.banks.clone(),
             ..*self
         }
     }
@@ -522,3 +650,289 @@ impl Clone for State {
 pub fn div_ceil(num: usize, by: usize) -> usize {
     (num + by - 1) / by
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixture::{Fixture, Frames};
+    use rng::Rng;
+
+    /// Map sizes the game is played on
+    const SIZES: [usize; 5] = [32, 40, 48, 56, 64];
+    const SEEDS: u64 = 40;
+
+    /// Fixture state with our ships added, holding random cargo on cells of random halite
+    fn random_state(seed: u64, rng: &mut Rng) -> State {
+        let game = Fixture { seed, ..Fixture::new(32, 32, 2, 12) }.game();
+        let mut state = State::from(&game, &Profiles::new(), &HashMap::new());
+        let max = state.constants.max_halite;
+
+        for ship in game.ships.values().filter(|ship| ship.owner == game.my_id) {
+            // Cargo at the extremes is where the rules are most likely to slip
+            let cargo = match rng.below(4) {
+                0 => 0,
+                1 => max,
+                _ => rng.below(max + 1),
+            };
+
+            state.add_ship(ship);
+            state.update_ship(ship.id, ship.position, cargo);
+            if !state.dropoffs.contains(&ship.position) {
+                state.update_hal(ship.position, rng.below(1001));
+            }
+        }
+
+        state
+    }
+
+    fn ship_ids(state: &State) -> Vec<ShipId> {
+        let mut ids: Vec<ShipId> = state.ships.keys().cloned().collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    /// Halite banked, carried and left on the map
+    fn total(state: &State) -> usize {
+        state.halite + state.ships.values().map(|&(_, hal)| hal).sum::<usize>() + state.map.iter().map(|(_, &hal)| hal).sum::<usize>()
+    }
+
+    fn burn(state: &State, pos: Position) -> usize {
+        state.halite(pos) / state.constants.move_cost_ratio
+    }
+
+    #[test]
+    fn moves_burn_halite_and_deposit_cargo() {
+        let mut rng = Rng::new(7);
+
+        for seed in 1..=SEEDS {
+            let state = random_state(seed, &mut rng);
+
+            for ship_id in ship_ids(&state) {
+                let (pos, hal) = state.ship(ship_id);
+                if hal < burn(&state, pos) {
+                    continue;
+                }
+
+                for dir in Direction::get_all_cardinals() {
+                    let mut moved = state.clone();
+                    moved.move_ship(ship_id, dir);
+
+                    let (new_pos, new_hal) = moved.ship(ship_id);
+                    assert_eq!(new_pos, state.normalize(pos.directional_offset(dir)));
+                    assert_eq!(total(&moved), total(&state) - burn(&state, pos));
+                    assert!(new_hal <= state.constants.max_halite);
+
+                    if state.dropoffs.contains(&new_pos) {
+                        assert_eq!(new_hal, 0);
+                        assert_eq!(moved.halite, state.halite + hal - burn(&state, pos));
+                    } else {
+                        assert_eq!(new_hal, hal - burn(&state, pos));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mining_takes_a_share_of_the_cell_up_to_capacity() {
+        let mut rng = Rng::new(11);
+
+        for seed in 1..=SEEDS {
+            let mut state = random_state(seed, &mut rng);
+
+            for ship_id in ship_ids(&state) {
+                let (pos, hal) = state.ship(ship_id);
+                let cell = state.halite(pos);
+                let expected = div_ceil(cell, state.constants.extract_ratio).min(state.constants.max_halite - hal);
+
+                // Inspired ships gain a bonus on top, but never beyond capacity
+                let mut inspired = state.clone();
+                inspired.inspired.set(pos, Some(1.0));
+                inspired.mine_ship(ship_id);
+                assert_eq!(inspired.halite(pos), cell - expected);
+                assert!(inspired.ship(ship_id).1 >= hal + expected);
+                assert!(inspired.ship(ship_id).1 <= state.constants.max_halite);
+
+                state.inspired.set(pos, None);
+                let before = total(&state);
+                state.mine_ship(ship_id);
+                assert_eq!(state.halite(pos), cell - expected);
+                assert_eq!(state.ship(ship_id), (pos, hal + expected));
+                assert_eq!(total(&state), before);
+            }
+        }
+    }
+
+    #[test]
+    fn dropoffs_spend_cargo_cell_and_bank() {
+        let mut rng = Rng::new(13);
+
+        for seed in 1..=SEEDS {
+            let state = random_state(seed, &mut rng);
+
+            for ship_id in ship_ids(&state) {
+                let (pos, hal) = state.ship(ship_id);
+                if state.dropoffs.contains(&pos) {
+                    continue;
+                }
+
+                let mut converted = state.clone();
+                converted.halite = state.constants.dropoff_cost;
+                let before = total(&converted);
+                converted.make_dropoff(ship_id);
+
+                assert!(!converted.ships.contains_key(&ship_id));
+                assert!(converted.dropoffs.contains(&pos));
+                assert_eq!(converted.taken[pos], None);
+                assert_eq!(converted.halite(pos), 0);
+                assert_eq!(converted.halite, hal + state.halite(pos));
+                assert_eq!(before - total(&converted), state.constants.dropoff_cost);
+            }
+        }
+    }
+
+    #[test]
+    fn actions_are_affordable_and_match_single_steps() {
+        let mut rng = Rng::new(17);
+
+        for seed in 1..=SEEDS {
+            let state = random_state(seed, &mut rng);
+
+            for ship_id in ship_ids(&state) {
+                let (pos, hal) = state.ship(ship_id);
+                let merged = MergedAction::new(ship_id, pos, hal);
+
+                for action in state.actions(&merged, false) {
+                    let dir = state.get_dir(pos, action.pos);
+                    assert!(dir == Direction::Still || hal >= burn(&state, pos), "Ship {} can't afford to leave {:?}", ship_id.0, (pos.x, pos.y));
+                    assert!(action.halite <= state.constants.max_halite);
+                    assert_eq!(action.breakdown.moves as usize, if dir == Direction::Still { 0 } else { burn(&state, pos) });
+
+                    // Applying the merged action agrees with stepping the ship
+                    let merged_state = state.apply_merged(&action);
+                    let mut stepped = state.clone();
+                    stepped.apply(Action::new(ship_id, dir, action.inspired, action.risk, false));
+
+                    assert_eq!(merged_state.ship(ship_id), stepped.ship(ship_id));
+                    assert_eq!(merged_state.halite, stepped.halite);
+                    assert_eq!(merged_state.halite(pos), stepped.halite(pos));
+                    assert_eq!(merged_state.taken[action.pos], Some(ship_id));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_walks_conserve_halite() {
+        let mut rng = Rng::new(19);
+
+        for seed in 1..=SEEDS {
+            let mut state = random_state(seed, &mut rng);
+            state.inspired = Grid::new(state.torus, None);
+            let mut burnt = 0;
+            let start = total(&state);
+
+            for _ in 0..200 {
+                let ids = ship_ids(&state);
+                let ship_id = ids[rng.below(ids.len())];
+                let (pos, hal) = state.ship(ship_id);
+                let dir = Direction::get_all_cardinals()[rng.below(4)];
+                let target = state.normalize(pos.directional_offset(dir));
+
+                if hal >= burn(&state, pos) && state.taken[target].is_none() {
+                    burnt += burn(&state, pos);
+                    state.move_ship(ship_id, dir);
+                } else {
+                    state.mine_ship(ship_id);
+                }
+
+                assert!(state.ship(ship_id).1 <= state.constants.max_halite);
+                assert_eq!(total(&state) + burnt, start);
+            }
+        }
+    }
+
+    #[test]
+    fn dropoff_cost_is_covered_by_cargo_and_cell_first() {
+        let pos = Position { x: 10, y: 10 };
+        let cost = Fixture::new(32, 32, 2, 0).state().constants.dropoff_cost;
+
+        // (cargo, cell halite, bank spent)
+        for &(cargo, cell, spent) in &[(0, 0, cost), (900, 500, cost - 1400), (1000, cost - 1000, 0), (1000, cost, 0)] {
+            let mut state = Fixture::new(32, 32, 2, 0).state();
+            state.halite = cost;
+            state.update_hal(pos, cell);
+            state.ships.insert(ShipId(0), (pos, cargo));
+            state.taken.set(pos, Some(ShipId(0)));
+
+            state.make_dropoff(ShipId(0));
+
+            assert_eq!(state.halite, cost - spent, "cargo {} cell {}", cargo, cell);
+            assert_eq!(state.halite(pos), 0);
+            assert_eq!(state.taken[pos], None);
+            assert!(state.dropoffs.contains(&pos));
+        }
+    }
+
+    #[test]
+    fn inspiration_follows_forecast_enemies_and_decays() {
+        let mut state = Fixture::new(32, 32, 2, 0).state();
+        state.enemies.clear();
+        state.enemy_targets.clear();
+
+        // Two enemies heading east towards a dropoff ten cells away
+        let target = Position { x: 20, y: 10 };
+        for (i, &pos) in [Position { x: 10, y: 10 }, Position { x: 10, y: 11 }].iter().enumerate() {
+            state.enemies.insert(pos, EnemyShip { owner: PlayerId(1), id: ShipId(100 + i), halite: 900 });
+            state.enemy_targets.insert(pos, target);
+        }
+        state.forecast_inspiration();
+
+        let (start, ahead) = (Position { x: 10, y: 10 }, Position { x: 15, y: 10 });
+        assert_eq!(state.inspired[start], Some(1.0));
+        assert_eq!(state.inspired[Position { x: 18, y: 10 }], None);
+        assert_eq!(state.inspire(start, 100), 100 + (100.0 * state.constants.inspired_bonus_multiplier) as usize);
+
+        let mut later = state.next();
+        for _ in 1..5 {
+            later = later.next();
+        }
+
+        // Five turns on the enemies are forecast five cells east, with less confidence
+        let confidence = INSPIRATION_DECAY.powi(5);
+        assert_eq!(later.inspired[ahead], Some(confidence));
+        assert_eq!(later.inspired[Position { x: 18, y: 10 }], Some(confidence));
+        assert_eq!(later.inspired[start], None);
+        assert_eq!(later.inspire(ahead, 100), 100 + (100.0 * later.constants.inspired_bonus_multiplier * confidence as f64) as usize);
+    }
+
+    #[test]
+    fn directions_agree_with_distance_on_every_map_size() {
+        let mut rng = Rng::new(23);
+
+        for &size in &SIZES {
+            let state = Fixture::new(size, size, 2, 0).state();
+
+            for _ in 0..2000 {
+                let a = Position { x: rng.below(size) as i32, y: rng.below(size) as i32 };
+                let b = Position { x: rng.below(size) as i32, y: rng.below(size) as i32 };
+                let distance = state.calculate_distance(a, b);
+
+                // Positions off the map by whole laps are the same cell
+                let (kx, ky) = (rng.below(5) as i32 - 2, rng.below(5) as i32 - 2);
+                let lapped = Position { x: a.x + kx * size as i32, y: a.y + ky * size as i32 };
+                assert_eq!(state.normalize(lapped), a);
+                assert_eq!(state.calculate_distance(lapped, b), distance);
+                assert!(distance <= size);
+
+                let dir = state.get_dir(a, b);
+                if a == b {
+                    assert_eq!(dir, Direction::Still);
+                } else {
+                    let next = state.normalize(a.directional_offset(dir));
+                    assert_eq!(state.calculate_distance(next, b) + 1, distance, "{:?} -> {:?} on {}", (a.x, a.y), (b.x, b.y), size);
+                }
+            }
+        }
+    }
+}
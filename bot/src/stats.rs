@@ -1,46 +1,218 @@
-use std::time::SystemTime;
+use std::cell::RefCell;
+use std::env;
 use std::time::Duration;
-use hlt::log::Log;
+use std::time::Instant;
+use hlt::log::{Log, Category};
+
+/// Time allowed for each turn in milliseconds
+const BUDGET_VAR: &str = "BOT_TURN_BUDGET_MS";
+const DEFAULT_BUDGET_MS: u64 = 2000;
+/// Share of the budget a turn can use before it is warned about
+const WARN_SHARE_VAR: &str = "BOT_TURN_WARN_SHARE";
+const DEFAULT_WARN_SHARE: f64 = 0.8;
+
+/// Totals for one named part of the turn over the whole game
+#[derive(Copy, Clone, Default)]
+struct Totals {
+    count: u32,
+    total: Duration,
+    /// Longest single run and the game turn it was on
+    max: (Duration, usize),
+    nodes: usize,
+}
+
+/// Times a part of the turn until dropped
+pub struct Span<'a> {
+    stats: &'a Stats,
+    name: &'static str,
+    start: Instant,
+    nodes: usize,
+}
+
+impl<'a> Span<'a> {
+    /// Count search nodes expanded within the span
+    pub fn nodes(&mut self, nodes: usize) {
+        self.nodes += nodes;
+    }
+}
+
+impl<'a> Drop for Span<'a> {
+    fn drop(&mut self) {
+        self.stats.record(self.name, self.start.elapsed(), self.nodes);
+    }
+}
 
 pub struct Stats {
-    start: SystemTime,
+    start: Instant,
     runtime: Duration,
     count: u32,
-    max: (Duration, u32),
+    /// Longest turn and its game turn number
+    max: (Duration, usize),
+    budget: Duration,
+    warn_share: f64,
+    spans: RefCell<Vec<(&'static str, Totals)>>,
+    /// Time in each span this turn and its longest single run
+    turn_spans: RefCell<Vec<(&'static str, Duration, Duration)>>,
+}
+
+impl Default for Stats {
+    fn default() -> Stats {
+        Stats::new()
+    }
 }
 
 impl Stats {
     pub fn new() -> Stats {
+        let budget = env::var(BUDGET_VAR).ok().and_then(|ms| ms.trim().parse().ok()).unwrap_or(DEFAULT_BUDGET_MS);
+        let warn_share = env::var(WARN_SHARE_VAR).ok().and_then(|share| share.trim().parse().ok()).unwrap_or(DEFAULT_WARN_SHARE);
+
         Stats {
-            start: SystemTime::now(),
+            start: Instant::now(),
             runtime: Duration::default(),
             count: 0,
             max: (Duration::default(), 0),
+            budget: Duration::from_millis(budget),
+            warn_share,
+            spans: RefCell::new(Vec::new()),
+            turn_spans: RefCell::new(Vec::new()),
         }
     }
 
     pub fn start(&mut self) {
-        self.start = SystemTime::now();
+        self.start = Instant::now();
+        self.turn_spans.borrow_mut().clear();
+    }
+
+    /// Replace the time allowed for each turn
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
     }
 
     pub fn remaining(&self) -> Duration {
-        let avail = Duration::from_secs(2);
-        let duration = SystemTime::now().duration_since(self.start).expect("Time goes forwards");
+        self.budget.checked_sub(self.start.elapsed()).unwrap_or_default()
+    }
+
+    /// Start timing a named part of the turn
+    pub fn span(&self, name: &'static str) -> Span<'_> {
+        Span { stats: self, name, start: Instant::now(), nodes: 0 }
+    }
+
+    fn record(&self, name: &'static str, duration: Duration, nodes: usize) {
+        let mut spans = self.spans.borrow_mut();
+        let index = match spans.iter().position(|&(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                spans.push((name, Totals::default()));
+                spans.len() - 1
+            }
+        };
 
-        avail - duration
+        let totals = &mut spans[index].1;
+        totals.count += 1;
+        totals.total += duration;
+        totals.nodes += nodes;
+
+        // The longest run is credited to a game turn once the turn ends
+        let mut turn_spans = self.turn_spans.borrow_mut();
+        match turn_spans.iter_mut().find(|&&mut (n, _, _)| n == name) {
+            Some(span) => {
+                span.1 += duration;
+                span.2 = span.2.max(duration);
+            }
+            None => turn_spans.push((name, duration, duration)),
+        }
     }
 
-    pub fn end(&mut self) {
-        let duration = SystemTime::now().duration_since(self.start).expect("Time goes forwards");
+    /// Finish timing the given game turn
+    pub fn end(&mut self, turn: usize) {
+        let duration = self.start.elapsed();
 
         self.runtime += duration;
-        self.max = self.max.max((duration, self.count));
+        self.max = self.max.max((duration, turn));
+
+        let mut spans = self.spans.borrow_mut();
+        for &(name, _, longest) in self.turn_spans.borrow().iter() {
+            if let Some(&mut (_, ref mut totals)) = spans.iter_mut().find(|&&mut (n, _)| n == name) {
+                totals.max = totals.max.max((longest, turn));
+            }
+        }
+        drop(spans);
 
         let mean = self.runtime / (self.count + 1);
 
-        Log::info(format!("Time: {:?}, mean: {:?}, max: {:?}, total: {:?}", duration, mean, self.max, self.runtime));
+        Log::info(Category::Timing, format_args!("Time: {:?}, mean: {:?}, max: {:?}, total: {:?}", duration, mean, self.max, self.runtime));
+
+        let share = duration.as_secs_f64() / self.budget.as_secs_f64();
+        if share > self.warn_share {
+            let mut turn_spans = self.turn_spans.borrow().clone();
+            turn_spans.sort_by_key(|&(_, d, _)| std::cmp::Reverse(d));
+
+            let spans: Vec<String> = turn_spans.iter().map(|(name, d, _)| format!("{}:{:?}", name, d)).collect();
+            Log::warn(Category::Timing, format_args!("Turn used {:.0}% of budget: {}", share * 100.0, spans.join(" ")));
+        }
 
         self.count += 1;
     }
 
+    /// Turn and span timings over the game as a JSON object, in milliseconds
+    pub fn report(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let mean = self.runtime / self.count.max(1);
+
+        let spans: Vec<String> = self.spans.borrow().iter()
+            .map(|&(name, totals)| format!(
+                "\"{}\": {{ \"count\": {}, \"total_ms\": {:.3}, \"max_ms\": {:.3}, \"max_turn\": {}, \"nodes\": {} }}",
+                name, totals.count, ms(totals.total), ms(totals.max.0), totals.max.1, totals.nodes))
+            .collect();
+
+        format!(
+            "{{ \"turns\": {}, \"total_ms\": {:.3}, \"mean_ms\": {:.3}, \"max_ms\": {:.3}, \"max_turn\": {}, \"budget_ms\": {:.3}, \"spans\": {{ {} }} }}",
+            self.count, ms(self.runtime), ms(mean), ms(self.max.0), self.max.1, ms(self.budget), spans.join(", "))
+    }
+
+    /// Log the totals of every span over the game
+    pub fn summary(&self) {
+        Log::info(Category::Timing, format_args!("Turns: {}, total: {:?}, max: {:?}", self.count, self.runtime, self.max));
+
+        for &(name, totals) in self.spans.borrow().iter() {
+            let mean = totals.total / totals.count.max(1);
+            Log::info(Category::Timing, format_args!(
+                "Span {}: count: {}, total: {:?}, mean: {:?}, max: {:?}, nodes: {}",
+                name, totals.count, totals.total, mean, totals.max, totals.nodes));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_accumulate_by_name() {
+        let mut stats = Stats::new();
+        stats.budget = Duration::default();
+
+        for turn in 7..10 {
+            stats.start();
+            let mut span = stats.span("path_ship");
+            span.nodes(10);
+            if turn == 8 {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            drop(span);
+            stats.span("collect_moves");
+            stats.end(turn);
+        }
+
+        let spans = stats.spans.borrow();
+        let names: Vec<&str> = spans.iter().map(|&(name, _)| name).collect();
+        assert_eq!(names, vec!["path_ship", "collect_moves"]);
+        assert_eq!(spans[0].1.count, 3);
+        assert_eq!(spans[0].1.nodes, 30);
+        assert_eq!(stats.remaining(), Duration::default());
+
+        // Longest runs are reported by game turn
+        assert_eq!(spans[0].1.max.1, 8);
+        assert_eq!(stats.max.1, 8);
+    }
 }
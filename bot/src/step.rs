@@ -0,0 +1,459 @@
+use hlt::*;
+use state::{State, EnemyShip, div_ceil};
+use collision::Collision;
+use std::collections::HashMap;
+
+pub enum Event {
+    /// Ships sunk by moving onto the same cell
+    Collision(Collision),
+    /// Cargo delivered to one of the owner's structures
+    Deposit { owner: PlayerId, ship_id: ShipId, amount: usize },
+    Spawn { owner: PlayerId, ship_id: ShipId, pos: Position },
+    Dropoff { owner: PlayerId, ship_id: ShipId, pos: Position },
+}
+
+enum Order {
+    Move(ShipId, Direction),
+    Construct(ShipId),
+    Spawn,
+}
+
+struct Sim {
+    owner: PlayerId,
+    id: ShipId,
+    pos: Position,
+    halite: usize,
+    inspired: bool,
+    moved: bool,
+}
+
+impl State {
+    /// Apply one turn of commands from every player the way the engine does
+    ///
+    /// Ships convert first, then move, then spawn. Ships sharing a cell sink,
+    /// dropping their cargo on the cell or into the owner's bank when the cell
+    /// is a structure. Ships which did not move mine, and ships on their own
+    /// structures deposit. Commands which cannot be afforded are ignored.
+    ///
+    /// Each command comes with the player who sent it, as a `Command` is only the
+    /// engine's text: a spawn names no ship, and a move for a ship the player does not
+    /// own is ignored by the engine, so neither can be applied without the sender.
+    pub fn step(&self, commands: &[(PlayerId, Command)]) -> (State, Vec<Event>) {
+        let mut state = self.clone();
+        let mut events = Vec::new();
+
+        let mut sims: Vec<Sim> = self.ships.iter()
+            .map(|&(id, (pos, halite))| Sim { owner: self.my_id, id, pos, halite, inspired: false, moved: false })
+            .chain(self.enemies.iter().map(|&(pos, enemy)| {
+                Sim { owner: enemy.owner, id: enemy.id, pos, halite: enemy.halite, inspired: false, moved: false }
+            }))
+            .collect();
+        sims.sort_by_key(|sim| sim.id.0);
+
+        // Inspiration comes from where ships are before anyone moves
+        if self.constants.inspiration_enabled {
+            let radius = self.constants.inspiration_radius;
+            for i in 0..sims.len() {
+                let count = sims.iter()
+                    .filter(|other| other.owner != sims[i].owner && self.calculate_distance(sims[i].pos, other.pos) <= radius)
+                    .count();
+                sims[i].inspired = count >= self.constants.inspiration_ship_count;
+            }
+        }
+
+        let orders: Vec<(PlayerId, Order)> = commands.iter()
+            .filter_map(|&(owner, ref command)| parse(command).map(|order| (owner, order)))
+            .collect();
+
+        let owned = |sims: &Vec<Sim>, owner: PlayerId, ship_id: ShipId| {
+            sims.iter().position(|sim| sim.id == ship_id && sim.owner == owner)
+        };
+
+        // Conversions
+        for &(owner, ref order) in &orders {
+            if let Order::Construct(ship_id) = *order {
+                let i = match owned(&sims, owner, ship_id) {
+                    Some(i) => i,
+                    None => continue,
+                };
+
+                let pos = sims[i].pos;
+                if state.is_structure(pos) {
+                    continue;
+                }
+
+                let cost = state.constants.dropoff_cost.saturating_sub(sims[i].halite + state.halite(pos));
+                if state.bank(owner) < cost {
+                    continue;
+                }
+
+                *state.bank_mut(owner) -= cost;
+                state.update_hal(pos, 0);
+                if owner == state.my_id {
+                    state.dropoffs.insert(pos);
+                } else {
+                    state.enemy_dropoffs.insert(pos, owner);
+                }
+
+                sims.remove(i);
+                events.push(Event::Dropoff { owner, ship_id, pos });
+            }
+        }
+
+        // Moves, paid for from the cell being left
+        for &(owner, ref order) in &orders {
+            if let Order::Move(ship_id, dir) = *order {
+                let i = match owned(&sims, owner, ship_id) {
+                    Some(i) => i,
+                    None => continue,
+                };
+
+                if dir == Direction::Still || sims[i].moved {
+                    continue;
+                }
+
+                let ratio = if sims[i].inspired {
+                    state.constants.inspired_move_cost_ratio
+                } else {
+                    state.constants.move_cost_ratio
+                };
+
+                let cost = state.halite(sims[i].pos) / ratio;
+                if sims[i].halite >= cost {
+                    sims[i].halite -= cost;
+                    sims[i].pos = state.normalize(sims[i].pos.directional_offset(dir));
+                    sims[i].moved = true;
+                }
+            }
+        }
+
+        // Spawns
+        for &(owner, ref order) in &orders {
+            if let Order::Spawn = *order {
+                let pos = match state.shipyards.get(&owner) {
+                    Some(&pos) => pos,
+                    None => continue,
+                };
+
+                if state.bank(owner) < state.constants.ship_cost {
+                    continue;
+                }
+
+                *state.bank_mut(owner) -= state.constants.ship_cost;
+                let ship_id = ShipId(state.next_ship_id);
+                state.next_ship_id += 1;
+
+                sims.push(Sim { owner, id: ship_id, pos, halite: 0, inspired: false, moved: true });
+                events.push(Event::Spawn { owner, ship_id, pos });
+            }
+        }
+
+        // Collisions
+        let mut cells: HashMap<Position, Vec<usize>> = HashMap::new();
+        for (i, sim) in sims.iter().enumerate() {
+            cells.entry(sim.pos).or_default().push(i);
+        }
+
+        let mut cells: Vec<(Position, Vec<usize>)> = cells.into_iter().filter(|(_, ships)| ships.len() > 1).collect();
+        cells.sort_by_key(|&(pos, _)| state.torus.index(pos));
+
+        let mut sunk = Vec::new();
+        for (pos, indices) in cells {
+            let cargo: usize = indices.iter().map(|&i| sims[i].halite).sum();
+
+            let dropped = match state.structure_owner(pos) {
+                Some(owner) => {
+                    *state.bank_mut(owner) += cargo;
+                    0
+                }
+                None => {
+                    let halite = state.halite(pos);
+                    state.update_hal(pos, halite + cargo);
+                    cargo
+                }
+            };
+
+            let ships = indices.iter().map(|&i| (sims[i].owner, sims[i].id)).collect();
+            events.push(Event::Collision(Collision { turn: state.turn, pos, ships, dropped }));
+            sunk.extend(indices);
+        }
+
+        let mut sims: Vec<Sim> = sims.into_iter()
+            .enumerate()
+            .filter(|(i, _)| !sunk.contains(i))
+            .map(|(_, sim)| sim)
+            .collect();
+
+        // Mining
+        for sim in sims.iter_mut().filter(|sim| !sim.moved) {
+            let halite = state.halite(sim.pos);
+            let cap = state.constants.max_halite - sim.halite;
+
+            let ratio = if sim.inspired {
+                state.constants.inspired_extract_ratio
+            } else {
+                state.constants.extract_ratio
+            };
+
+            let mined = div_ceil(halite, ratio).min(cap);
+            state.update_hal(sim.pos, halite - mined);
+            sim.halite += mined;
+
+            if sim.inspired {
+                let bonus = (mined as f64 * state.constants.inspired_bonus_multiplier) as usize;
+                sim.halite += bonus.min(state.constants.max_halite - sim.halite);
+            }
+        }
+
+        // Deposits
+        for sim in sims.iter_mut() {
+            if sim.halite > 0 && state.structure_owner(sim.pos) == Some(sim.owner) {
+                *state.bank_mut(sim.owner) += sim.halite;
+                events.push(Event::Deposit { owner: sim.owner, ship_id: sim.id, amount: sim.halite });
+                sim.halite = 0;
+            }
+        }
+
+        // Rebuild ship positions from the survivors
+        for &(ship_id, _) in self.ships.iter() {
+            state.rm_ship(ship_id);
+        }
+
+        let enemy_targets = state.enemy_targets.clone();
+        state.enemies.clear();
+        state.enemy_targets.clear();
+
+        for sim in &sims {
+            if sim.owner == state.my_id {
+                state.ships.insert(sim.id, (sim.pos, sim.halite));
+                state.taken.set(sim.pos, Some(sim.id));
+            } else {
+                state.enemies.insert(sim.pos, EnemyShip { owner: sim.owner, id: sim.id, halite: sim.halite });
+
+                let before = self.enemies.iter().find(|&&(_, enemy)| enemy.id == sim.id).map(|&(pos, _)| pos);
+                if let Some(&target) = before.and_then(|pos| enemy_targets.get(&pos)) {
+                    state.enemy_targets.insert(sim.pos, target);
+                }
+            }
+        }
+
+        // Positions are now known again, so forecasts restart from here
+        state.turn += 1;
+        state.start = state.turn;
+        state.arrivals.clear();
+        state.forecast_inspiration();
+
+        (state, events)
+    }
+
+    fn bank(&self, owner: PlayerId) -> usize {
+        if owner == self.my_id {
+            self.halite
+        } else {
+            self.banks.get(&owner).cloned().unwrap_or(0)
+        }
+    }
+
+    fn bank_mut(&mut self, owner: PlayerId) -> &mut usize {
+        if owner == self.my_id {
+            &mut self.halite
+        } else {
+            self.banks.entry(owner).or_insert(0)
+        }
+    }
+
+    fn structure_owner(&self, pos: Position) -> Option<PlayerId> {
+        if self.dropoffs.contains(&pos) {
+            Some(self.my_id)
+        } else {
+            self.enemy_dropoffs.get(&pos).cloned()
+        }
+    }
+
+    fn is_structure(&self, pos: Position) -> bool {
+        self.structure_owner(pos).is_some()
+    }
+}
+
+fn parse(command: &Command) -> Option<Order> {
+    let mut tokens = command.0.split_whitespace();
+    match tokens.next()? {
+        "g" => Some(Order::Spawn),
+        "c" => tokens.next()?.parse().ok().map(|id| Order::Construct(ShipId(id))),
+        "m" => {
+            let id = tokens.next()?.parse().ok()?;
+            let dir = Direction::from_char_encoding(tokens.next()?.chars().next()?)?;
+            Some(Order::Move(ShipId(id), dir))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixture::{Fixture, Frames};
+
+    const ME: PlayerId = PlayerId(0);
+    const ENEMY: PlayerId = PlayerId(1);
+
+    /// Empty 16x16 two player state with shipyards at (4, 8) and (12, 8)
+    fn state() -> State {
+        let mut state = Fixture::new(16, 16, 2, 0).state();
+        for i in 0..state.torus.size() {
+            let pos = state.torus.position(i);
+            state.update_hal(pos, 100);
+        }
+        state.update_hal(Position { x: 4, y: 8 }, 0);
+        state.update_hal(Position { x: 12, y: 8 }, 0);
+        state.halite = 5000;
+        state.banks.insert(ENEMY, 5000);
+        state
+    }
+
+    fn ship(state: &mut State, id: usize, x: i32, y: i32, halite: usize) {
+        let pos = Position { x, y };
+        state.ships.insert(ShipId(id), (pos, halite));
+        state.taken.set(pos, Some(ShipId(id)));
+    }
+
+    fn enemy(state: &mut State, id: usize, x: i32, y: i32, halite: usize) {
+        state.enemies.insert(Position { x, y }, EnemyShip { owner: ENEMY, id: ShipId(id), halite });
+    }
+
+    fn mv(owner: PlayerId, id: usize, dir: Direction) -> (PlayerId, Command) {
+        (owner, Command::move_ship(ShipId(id), dir))
+    }
+
+    #[test]
+    fn moving_costs_a_tenth_of_the_cell_left() {
+        let mut state = state();
+        ship(&mut state, 0, 0, 0, 50);
+
+        let (next, _) = state.step(&[mv(ME, 0, Direction::East)]);
+        assert_eq!(next.ship(ShipId(0)), (Position { x: 1, y: 0 }, 40));
+        assert_eq!(next.halite(Position { x: 0, y: 0 }), 100);
+        assert_eq!(next.taken[Position { x: 1, y: 0 }], Some(ShipId(0)));
+        assert_eq!(next.taken[Position { x: 0, y: 0 }], None);
+    }
+
+    #[test]
+    fn ships_which_cannot_afford_to_move_mine() {
+        let mut state = state();
+        ship(&mut state, 0, 0, 0, 5);
+
+        let (next, _) = state.step(&[mv(ME, 0, Direction::East)]);
+        assert_eq!(next.ship(ShipId(0)), (Position { x: 0, y: 0 }, 30));
+        assert_eq!(next.halite(Position { x: 0, y: 0 }), 75);
+    }
+
+    #[test]
+    fn mining_rounds_up_and_stops_at_capacity() {
+        let mut state = state();
+        state.update_hal(Position { x: 0, y: 0 }, 10);
+        state.update_hal(Position { x: 2, y: 0 }, 100);
+        ship(&mut state, 0, 0, 0, 0);
+        ship(&mut state, 1, 2, 0, 990);
+
+        let (next, _) = state.step(&[]);
+        assert_eq!(next.ship(ShipId(0)).1, 3);
+        assert_eq!(next.halite(Position { x: 0, y: 0 }), 7);
+        assert_eq!(next.ship(ShipId(1)).1, 1000);
+        assert_eq!(next.halite(Position { x: 2, y: 0 }), 90);
+    }
+
+    #[test]
+    fn inspired_ships_mine_a_bonus() {
+        let mut state = state();
+        ship(&mut state, 0, 0, 0, 0);
+        enemy(&mut state, 1, 2, 0, 0);
+        enemy(&mut state, 2, 0, 3, 0);
+
+        let (next, _) = state.step(&[]);
+        assert_eq!(next.ship(ShipId(0)).1, 75);
+        assert_eq!(next.halite(Position { x: 0, y: 0 }), 75);
+    }
+
+    #[test]
+    fn collisions_sink_every_ship_and_drop_cargo() {
+        let mut state = state();
+        ship(&mut state, 0, 0, 0, 300);
+        enemy(&mut state, 1, 2, 0, 200);
+
+        let (next, events) = state.step(&[mv(ME, 0, Direction::East), mv(ENEMY, 1, Direction::West)]);
+        assert!(next.ships.is_empty() && next.enemies.is_empty());
+        assert_eq!(next.taken[Position { x: 1, y: 0 }], None);
+        assert_eq!(next.halite(Position { x: 1, y: 0 }), 100 + 290 + 190);
+
+        match events.as_slice() {
+            [Event::Collision(collision)] => {
+                assert_eq!(collision.pos, Position { x: 1, y: 0 });
+                assert_eq!(collision.ships.len(), 2);
+                assert_eq!(collision.dropped, 480);
+            }
+            _ => panic!("Expected a single collision"),
+        }
+    }
+
+    #[test]
+    fn collisions_on_structures_credit_the_owner() {
+        let mut state = state();
+        ship(&mut state, 0, 4, 7, 300);
+        enemy(&mut state, 1, 4, 9, 200);
+
+        let (next, _) = state.step(&[mv(ME, 0, Direction::South), mv(ENEMY, 1, Direction::North)]);
+        assert_eq!(next.halite, 5000 + 290 + 190);
+        assert_eq!(next.halite(Position { x: 4, y: 8 }), 0);
+        assert_eq!(next.banks[&ENEMY], 5000);
+    }
+
+    #[test]
+    fn spawning_onto_an_occupied_shipyard_sinks_both() {
+        let mut state = state();
+        ship(&mut state, 0, 4, 8, 0);
+
+        let (next, events) = state.step(&[(ME, Command::spawn_ship())]);
+        assert!(next.ships.is_empty());
+        assert_eq!(next.halite, 4000);
+        assert!(events.iter().any(|e| matches!(*e, Event::Spawn { .. })));
+        assert!(events.iter().any(|e| matches!(*e, Event::Collision(_))));
+    }
+
+    #[test]
+    fn spawns_need_a_full_ship_cost() {
+        let mut state = state();
+        state.halite = 999;
+
+        let (next, events) = state.step(&[(ME, Command::spawn_ship())]);
+        assert!(next.ships.is_empty() && events.is_empty());
+        assert_eq!(next.halite, 999);
+    }
+
+    #[test]
+    fn returning_ships_deposit() {
+        let mut state = state();
+        ship(&mut state, 0, 3, 8, 500);
+
+        let (next, events) = state.step(&[mv(ME, 0, Direction::East)]);
+        assert_eq!(next.ship(ShipId(0)), (Position { x: 4, y: 8 }, 0));
+        assert_eq!(next.halite, 5000 + 490);
+
+        match events.as_slice() {
+            [Event::Deposit { owner, amount, .. }] => assert_eq!((*owner, *amount), (ME, 490)),
+            _ => panic!("Expected a single deposit"),
+        }
+    }
+
+    #[test]
+    fn dropoffs_are_paid_for_with_cargo_and_cell_halite() {
+        let mut state = state();
+        state.update_hal(Position { x: 0, y: 0 }, 600);
+        ship(&mut state, 0, 0, 0, 400);
+
+        let (next, _) = state.step(&[(ME, Command::transform_ship_into_dropoff_site(ShipId(0)))]);
+        assert!(next.ships.is_empty());
+        assert!(next.dropoffs.contains(&Position { x: 0, y: 0 }));
+        assert_eq!(next.halite, 5000 - 3000);
+        assert_eq!(next.halite(Position { x: 0, y: 0 }), 0);
+    }
+}
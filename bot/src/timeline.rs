@@ -1,27 +1,53 @@
 use hlt::*;
-use std::cell::{RefCell, RefMut};
+use std::cell::{Cell, RefCell, RefMut};
 use state::State;
+use grid::Grid;
 use std::collections::HashSet;
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::time::Duration;
-use action::{Action, MergedAction};
+use std::env;
+use action::{Action, Breakdown, MergedAction};
 use pathfinding::directed::astar::astar;
 use pathfinding::kuhn_munkres::kuhn_munkres;
 use pathfinding::matrix::Matrix;
 use cost::Cost;
 use stats::Stats;
+use profile::Profiles;
+use collision::{Spill, SPILL_PRIORITY};
+use fallback::Fallback;
+use overlay::{self, Layer};
+use trace::Trace;
+use rollout::{Rollout, Plan};
 
 const MAX_LOOKAHEAD: usize = 40;
 const MIN_LOOKAHEAD: usize = 20;
 const MIN_DROPOFF_DIST: usize = 16;
 const MAX_DROPOFF_DIST: usize = 22;
+/// Extra spacing kept from players due to build their next dropoff
+const EXPANSION_MARGIN: usize = 4;
 const KERNEL_SIZE: i32 = 30;
 const TARGET_DELTA: i32 = 70;
 const SHIP_DIST_RATIO: usize = 4;
 const SHIP_DROPOFF_RATIO: usize = 15;
 const PATH_TIMEOUT: usize = 16;
+/// Futures sampled to judge whether a dropoff builder survives the trip
+const DROPOFF_SAMPLES: usize = 16;
+/// Mean losses above which a dropoff builder is kept home rather than sent
+const MAX_BUILDER_LOSSES: f32 = 0.5;
+/// Futures sampled to choose between a contested ship's move and holding still
+const CONTEST_SAMPLES: usize = 8;
+/// Turns of a contested ship's path the rollout plays out
+const CONTEST_HORIZON: usize = 4;
+/// Time left in the turn below which contested ships follow their paths unchecked
+const CONTEST_MIN_REMAINING_MS: u64 = 300;
 const SCALE_FACTOR: f64 = SHIP_DIST_RATIO as f64 * 20.0;
+/// How conflicting moves are resolved, `signal` or `matching`; signal by default
+const RESOLVER_VAR: &str = "BOT_RESOLVER";
+
+lazy_static! {
+    static ref RESOLVER: Resolver = env::var(RESOLVER_VAR).ok().and_then(|spec| Resolver::parse(&spec)).unwrap_or_default();
+}
 
 fn sig(total: usize, f: usize, scale: f64) -> usize {
     let factor = -1.0 / (1.0 + (4.0 * (1.0 - f as f64 / scale)).exp()) + 1.0;
@@ -34,6 +60,10 @@ pub struct Timeline {
     unpathed: Vec<(MergedAction, usize)>,
     mined: HashMap<Position, usize>,
     target_dropoffs: HashMap<ShipId, (Position, usize)>,
+    poisoned: HashMap<ShipId, usize>,
+    costs: HashMap<ShipId, Breakdown>,
+    /// Nodes expanded by the last path search
+    expansions: usize,
     spawn_action: MergedAction,
     constants: Constants,
     nav: Navi,
@@ -46,6 +76,9 @@ impl Timeline {
         game: &Game,
         crashed: Vec<Position>,
         paths: &mut HashMap<ShipId, VecDeque<Action>>,
+        profiles: &Profiles,
+        spills: &HashMap<Position, Spill>,
+        stats: &Stats,
     ) -> Timeline {
         // Prune crashed ships and completed paths
         let me = game.players.iter().find(|p| p.id == game.my_id).unwrap();
@@ -54,17 +87,23 @@ impl Timeline {
 
         let mut nav = Navi::new(game.map.width, game.map.height);
         nav.update_frame(game);
+        nav.resolver = *RESOLVER;
 
         // Add each ship to initial state
-        let mut state = State::from(&game);
+        let mut state = State::from(&game, profiles, spills);
+        state.lanes = nav.traffic.lanes.iter().map(|(&pos, &lane)| (pos, lane)).collect();
         for ship_id in paths.keys() {
             let ship = &game.ships[ship_id];
             state.add_ship(ship);
         }
 
+        // Ships in id order, so turns replay the same way every run
+        let mut ordered: Vec<ShipId> = me.ship_ids.clone();
+        ordered.sort_by_key(|ship_id| ship_id.0);
+
         // Transform path into set of actions at each timestep
         let mut actions: Vec<Vec<Action>> = Vec::new();
-        for (&_ship_id, path) in paths.iter() {
+        for path in ordered.iter().filter_map(|ship_id| paths.get(ship_id)) {
             for (i, &action) in path.iter().enumerate() {
                 if i >= actions.len() {
                     actions.push(Vec::new());
@@ -94,6 +133,8 @@ impl Timeline {
         let mut building = HashSet::new();
         let mut save = 0;
 
+        let replay = stats.span("timeline.replay");
+
         // Create timeline states from sequence of actions
         for (i, step) in actions.into_iter().enumerate() {
             // Initialize next state
@@ -127,7 +168,7 @@ impl Timeline {
                         }
                     } else {
                         // Poison the path at timestep i if the current action cannot be applied
-                        Log::warn(format!("P(s:{},t:{})", action.ship_id.0, i));
+                        Log::warn(Category::Planner, format_args!("P(s:{},t:{})", action.ship_id.0, i));
                         poisoned.insert(action.ship_id, i);
                     }
                 }
@@ -153,14 +194,16 @@ impl Timeline {
             // Add blank terminal state
             let mut state = timeline.last().unwrap().next();
 
-            state.taken.clear();
+            state.taken = Grid::new(state.torus, None);
             state.ships.clear();
 
             timeline.push(state);
         }
 
+        drop(replay);
+
         // Truncate poisoned paths
-        for (ship_id, t) in poisoned {
+        for (&ship_id, &t) in &poisoned {
             if t == 0 {
                 paths.remove(&ship_id);
             } else {
@@ -169,7 +212,7 @@ impl Timeline {
         }
 
         let mut unpathed = Vec::new();
-        for ship_id in ship_ids {
+        for ship_id in ordered {
             if !paths.contains_key(&ship_id) {
                 let ship = &game.ships[&ship_id];
                 let action = MergedAction::new(ship_id, ship.position, ship.halite);
@@ -182,7 +225,7 @@ impl Timeline {
                         let action = MergedAction::new(ship_id, pos, hal);
                         unpathed.push((action, len));
                     } else {
-                        Log::info(format!("ShipDel:{}", ship_id.0));
+                        Log::info(Category::Planner, format_args!("ShipDel:{}", ship_id.0));
                     }
                 }
             }
@@ -194,27 +237,13 @@ impl Timeline {
         let spawn_action = MergedAction::spawn(me.shipyard.position);
         let constants = game.constants.clone();
 
-        let mut richness: HashMap<Position, usize> = HashMap::new();
-
-        let k = KERNEL_SIZE / 2;
-        for cell in game.map.iter() {
-            let pos = cell.position;
-            let mut sum = 0;
-            for i in 0..=KERNEL_SIZE {
-                for j in 0..=KERNEL_SIZE {
-                    let x = pos.x + i - k;
-                    let y = pos.y + j - k;
-                    let d = ((i - k).abs() + (j - k).abs()) as usize + 1;
-                    
-                    sum += game.map.at_position(&Position { x, y }).halite / d;
-                }
-            }
-            richness.insert(pos, sum);
-        }
+        let span = stats.span("timeline.richness");
+        let richness = richness(game, spills);
+        drop(span);
 
         let mut max = 0;
         for dropoff in timeline[0].dropoffs.iter().chain(building.iter()) {
-            max = max.max(richness[dropoff]);
+            max = max.max(richness[*dropoff]);
         }
 
         let num_ships = me.ship_ids.len();
@@ -235,27 +264,28 @@ impl Timeline {
 
         let mut max_prime: Option<(Position, usize)> = None;
         if ship_ids.len() / SHIP_DROPOFF_RATIO >= dropoffs.len() {
-            for (&pos, &hal) in richness.iter() {
+            for (pos, &hal) in richness.iter() {
                 let rate = hal * num_ships;
                 if rate > cur_rate {
-                    let min_dist = game.dropoffs.values()
-                        .map(|d| d.position)
-                        .chain(game.players.iter().map(|p| p.shipyard.position))
-                        .map(|p| game.map.calculate_distance(&pos, &p))
+                    let state = &timeline[0];
+                    let own_dist = state.calculate_distance(pos, state.nearest_dropoff(pos));
+
+                    let enemy_players = state.enemy_players();
+                    let enemy_dist = enemy_players.iter()
+                        .filter_map(|&player_id| {
+                            let margin = if profiles.dropoff_due(player_id, game.turn_number) { EXPANSION_MARGIN } else { 0 };
+                            state.nearest_enemy_dropoff(player_id, pos)
+                                .map(|p| state.calculate_distance(pos, p).saturating_sub(margin))
+                        })
                         .min()
-                        .unwrap();
+                        .unwrap_or(own_dist);
 
-                    let max_dist = game.dropoffs.values()
-                        .filter(|d| d.owner == game.my_id)
-                        .map(|d| d.position)
-                        .chain(std::iter::once(me.shipyard.position))
-                        .map(|p| game.map.calculate_distance(&pos, &p))
-                        .min()
-                        .unwrap();
+                    let min_dist = own_dist.min(enemy_dist);
+                    let max_dist = own_dist;
 
                     if min_dist >= MIN_DROPOFF_DIST && max_dist <= MAX_DROPOFF_DIST {
                         max_prime = max_prime.map(|prev| if rate > prev.1 { (pos, rate) } else { prev }).or(Some((pos, rate)));
-                        Log::log(pos, format!("_r{}_", rate), "fuchsia");
+                        Log::log(Category::Dropoff, pos, format_args!("_r{}_", rate), "fuchsia");
                     }
                 }
             }
@@ -267,6 +297,7 @@ impl Timeline {
             save += constants.dropoff_cost;
         }
 
+        let span = stats.span("timeline.assignment");
         let rows = ship_ids.len();
         let columns = dropoffs.len() * ship_ids.len();
 
@@ -276,7 +307,7 @@ impl Timeline {
             
             for &(pos, t) in &dropoffs {
                 let dist = game.map.calculate_distance(&ship_pos, &pos).max(t);
-                let dropoff_value = richness[&pos];
+                let dropoff_value = richness[pos];
 
                 for num_ships in 0..ship_ids.len() {
                     let value = sig(dropoff_value, dist + num_ships * SHIP_DIST_RATIO, SCALE_FACTOR);
@@ -296,25 +327,88 @@ impl Timeline {
             let dropoff_index = dropoff_slot_index / ship_ids.len();
             let dropoff_pos_t = dropoffs[dropoff_index];
 
-            Log::msg(dropoff_pos_t.0, format!("_sid{}_", ship_id.0));
-            Log::msg(ship_pos, format!("_d({},{},{})_", dropoff_pos_t.0.x, dropoff_pos_t.0.y, dropoff_pos_t.1));
+            Log::msg(Category::Dropoff, dropoff_pos_t.0, format_args!("_sid{}_", ship_id.0));
+            Log::msg(Category::Dropoff, ship_pos, format_args!("_d({},{},{})_", dropoff_pos_t.0.x, dropoff_pos_t.0.y, dropoff_pos_t.1));
 
             target_dropoffs.insert(ship_id, dropoff_pos_t);
         }
 
-        Timeline {
+        drop(span);
+
+        let timeline = Timeline {
             timeline: RefCell::new(timeline),
             unpathed,
             mined,
             target_dropoffs,
+            poisoned,
+            costs: HashMap::new(),
+            expansions: 0,
             spawn_action,
             constants,
             nav,
             prime,
             save,
+        };
+
+        timeline.log_layers(game, &richness);
+        timeline
+    }
+
+    /// Draw whichever heatmap layers are enabled
+    fn log_layers(&self, game: &Game, richness: &Grid<usize>) {
+        overlay::heatmap(Layer::Richness, richness.iter().map(|(pos, &value)| (pos, value as f32)));
+
+        if Layer::Inspired.enabled() {
+            let state = self.state(1);
+            overlay::heatmap(Layer::Inspired, state.inspired.iter().filter_map(|(pos, &c)| c.map(|c| (pos, c))));
+        }
+
+        if Layer::Danger.enabled() {
+            let state = self.state(0);
+            let mut danger = Grid::new(state.torus, 0.0f32);
+
+            for &(pos, enemy) in state.enemies.iter() {
+                let factor = state.risk_factors.get(&enemy.owner).cloned().unwrap_or(1.0);
+                for cell in state.torus.ball(state.forecast_enemy(pos, 1), 1) {
+                    danger.set(cell, danger[cell].max(factor));
+                }
+            }
+
+            overlay::heatmap(Layer::Danger, danger.iter().map(|(pos, &value)| (pos, value)));
+        }
+
+        if Layer::Occupancy.enabled() {
+            let timeline = self.timeline.borrow();
+            let mut occupancy = Grid::new(timeline[0].torus, 0);
+
+            for state in timeline.iter() {
+                for (pos, taken) in state.taken.iter() {
+                    if taken.is_some() {
+                        occupancy.set(pos, occupancy[pos] + 1);
+                    }
+                }
+            }
+
+            overlay::heatmap(Layer::Occupancy, occupancy.iter().map(|(pos, &count)| (pos, count as f32)));
+        }
+
+        if Layer::Assignment.enabled() {
+            let mut dropoffs: Vec<Position> = self.target_dropoffs.values().map(|&(pos, _)| pos).collect();
+            dropoffs.sort_by_key(|pos| (pos.x, pos.y));
+            dropoffs.dedup();
+
+            overlay::groups(Layer::Assignment, self.target_dropoffs.iter().map(|(ship_id, &(dropoff, _))| {
+                let group = dropoffs.iter().position(|&pos| pos == dropoff).unwrap();
+                (game.ships[ship_id].position, group, format!("({},{})", dropoff.x, dropoff.y))
+            }));
         }
     }
 
+    /// Ships whose paths could not be followed, with the timestep they failed at
+    pub fn poisoned(&self) -> &HashMap<ShipId, usize> {
+        &self.poisoned
+    }
+
     /// State at timestep t
     pub fn state(&self, t: usize) -> RefMut<State> {
         let mut timeline = self.timeline.borrow_mut();
@@ -338,7 +432,7 @@ impl Timeline {
         }
     }
 
-    fn path(
+    pub fn path(
         &mut self,
         initial_action: MergedAction,
         start: usize,
@@ -349,10 +443,12 @@ impl Timeline {
         let initial_pos = initial_action.pos;
 
         merged.borrow_mut().insert((initial_pos, start), initial_action);
+        let expansions = Cell::new(0);
 
         let path = astar(
                 &(initial_pos, start, 0),
                 |&key| {
+                    expansions.set(expansions.get() + 1);
                     let local_key = (key.0, key.1);
                     let successors: Vec<MergedAction> = {
                         let parent = &merged.borrow()[&local_key];
@@ -415,17 +511,50 @@ impl Timeline {
                 }
             );
 
+        self.expansions = expansions.get();
+
         let mut merged = merged.into_inner();
         path.map(|(path, _)| path.into_iter().map(|(pos, t, _)| merged.remove(&(pos, t)).unwrap()).collect())
     }
 
+    /// Keep the shipyard clear next turn if we can afford to spawn
+    pub fn reserve_spawn(&mut self) -> bool {
+        let spawn_action = self.spawn_action.clone();
+        let taken = self.state(1).taken[spawn_action.pos].is_some();
+        let can_afford = self.state(0).halite >= self.constants.ship_cost + self.save;
+
+        if can_afford && !taken {
+            self.state(1).taken.set(spawn_action.pos, Some(spawn_action.ship_id));
+            self.nav.traffic.reserve(&spawn_action.pos);
+            Log::debug(Category::Spawn, format_args!("Reserved shipyard ({},{})", spawn_action.pos.x, spawn_action.pos.y));
+            true
+        } else {
+            Log::debug(Category::Spawn, format_args!("Shipyard not reserved: afford {}, taken {}", can_afford, taken));
+            false
+        }
+    }
+
     pub fn spawn_ship(&mut self) -> bool {
         let spawn_action = self.spawn_action.clone();
-        let taken = self.state(1).taken.contains_key(&spawn_action.pos);
+        let reserved = self.state(1).taken[spawn_action.pos] == Some(spawn_action.ship_id);
+        let clear = self.nav.get(&spawn_action.pos).is_none();
         let can_afford = self.state(0).halite >= self.constants.ship_cost + self.save;
         let target = (spawn_action.pos, 0);
 
-        can_afford && !taken && self.path(spawn_action, 1, target, MIN_LOOKAHEAD).is_some()
+        let spawn = can_afford && reserved && clear && self.path(spawn_action.clone(), 1, target, MIN_LOOKAHEAD).is_some();
+
+        // Give the shipyard back to the planned ships if the spawn isn't issued
+        if reserved && !spawn {
+            self.state(1).taken.set(spawn_action.pos, None);
+            self.nav.traffic.release(&spawn_action.pos);
+            Log::debug(Category::Spawn, format_args!("Released shipyard: afford {}, clear {}", can_afford, clear));
+        }
+
+        if spawn {
+            Log::info(Category::Spawn, format_args!("Spawning with {} halite", self.state(0).halite));
+        }
+
+        spawn
     }
 
     fn earliest_build(&self, pos: Position) -> usize {
@@ -439,7 +568,9 @@ impl Timeline {
         return MAX_LOOKAHEAD + 1;
     }
 
-    pub fn make_dropoff(&mut self, paths: &mut HashMap<ShipId, VecDeque<Action>>) {
+    pub fn make_dropoff(&mut self, paths: &mut HashMap<ShipId, VecDeque<Action>>, stats: &Stats) {
+        let mut span = stats.span("make_dropoff");
+
         if self.unpathed.len() == 0 {
             return;
         }
@@ -464,7 +595,10 @@ impl Timeline {
         let (initial_action, start) = self.unpathed.remove(action_index);
         let ship_id = initial_action.ship_id;
 
-        if let Some(path) = self.path(initial_action, start, (target, 0), MAX_LOOKAHEAD) {
+        let path = self.path(initial_action, start, (target, 0), MAX_LOOKAHEAD);
+        span.nodes(self.expansions);
+
+        if let Some(path) = path {
             if path.is_empty() {
                 return;
             }
@@ -485,12 +619,22 @@ impl Timeline {
                 return;
             }
 
+            let losses = self.builder_losses(start, &path);
+            if losses > MAX_BUILDER_LOSSES {
+                Log::info(Category::Dropoff, format_args!("Builder {} expected to sink {:.2} times, not building", ship_id.0, losses));
+                return;
+            }
+
             self.save = self.constants.dropoff_cost - ship_halite - tile_halite;
 
             for (i, diff) in path.iter().enumerate() {
                 self.state(start + i).apply_merged_mut(diff);
             }
 
+            for (i, edge) in path.windows(2).enumerate() {
+                self.state(start + i + 1).record_move(edge[0].pos, edge[1].pos);
+            }
+
             {
                 let dropoff_state = &mut self.state(start + i + 1);
                 dropoff_state.apply_merged_mut(end);
@@ -507,7 +651,7 @@ impl Timeline {
                 let action = Action::new(next.ship_id, dir, next.inspired, next.risk, false);
 
                 actions.push_back(action);
-                Log::log(next.pos, format!("-ship[{}:t{}:h{}]-", ship_id.0, start + i, next.halite), "yellow");
+                Log::log(Category::Planner, next.pos, format_args!("-ship[{}:t{}:h{}]-", ship_id.0, start + i, next.halite), "yellow");
             }
 
             assert!(!actions.is_empty());
@@ -516,7 +660,7 @@ impl Timeline {
             last.dir = Direction::Still;
             last.dropoff = true;
             actions.push_back(last);
-            Log::info(format!("_md{}_", last.ship_id.0));
+            Log::info(Category::Dropoff, format_args!("_md{}_", last.ship_id.0));
 
             if !paths.contains_key(&ship_id) {
                 paths.insert(ship_id, actions);
@@ -528,7 +672,62 @@ impl Timeline {
         }
     }
 
-    pub fn path_ships(&mut self, paths: &mut HashMap<ShipId, VecDeque<Action>>, stats: &Stats) -> Vec<Command> {
+    /// State at t with ship as the only one of ours, so rollouts judge it alone
+    fn solo_state(&self, t: usize, ship_id: ShipId, pos: Position, halite: usize) -> State {
+        let mut state = self.state(t).clone();
+        let ours: Vec<ShipId> = state.ships.keys().cloned().collect();
+        for ship_id in ours {
+            state.rm_ship(ship_id);
+        }
+        state.ships.insert(ship_id, (pos, halite));
+        state.taken.set(pos, Some(ship_id));
+        state
+    }
+
+    /// Mean times the builder following path from start is sunk before it converts,
+    /// with enemies played out by rollouts and no other ships of ours about
+    fn builder_losses(&self, start: usize, path: &[MergedAction]) -> f32 {
+        let builder = &path[0];
+        let state = self.solo_state(start, builder.ship_id, builder.pos, builder.halite);
+
+        let mut actions: VecDeque<Action> = path.windows(2)
+            .map(|edge| Action::new(builder.ship_id, state.get_dir(edge[0].pos, edge[1].pos), false, false, false))
+            .collect();
+        actions.push_back(Action::new(builder.ship_id, Direction::Still, false, false, true));
+
+        let mut plan = Plan::new();
+        let horizon = actions.len();
+        plan.insert(builder.ship_id, actions);
+
+        Rollout::new(DROPOFF_SAMPLES, horizon).evaluate(&state, &plan).losses
+    }
+
+    /// Whether a ship whose next move is next to an enemy does better holding still a turn
+    /// before following its path, comparing both plans over the same rollouts
+    fn holds_back(&self, ship_id: ShipId, path: &VecDeque<Action>) -> bool {
+        let (pos, halite) = self.state(0).ship(ship_id);
+        let state = self.solo_state(0, ship_id, pos, halite);
+
+        let go: VecDeque<Action> = path.iter().take(CONTEST_HORIZON).cloned().collect();
+        let hold: VecDeque<Action> = std::iter::once(Action::new(ship_id, Direction::Still, false, false, false))
+            .chain(path.iter().take(CONTEST_HORIZON - 1).cloned())
+            .collect();
+
+        let plans: Vec<Plan> = vec![go, hold].into_iter()
+            .map(|actions| std::iter::once((ship_id, actions)).collect())
+            .collect();
+        let scores = Rollout::new(CONTEST_SAMPLES, CONTEST_HORIZON).compare(&state, &plans);
+
+        let ship_cost = self.constants.ship_cost;
+        scores[1].value(ship_cost) > scores[0].value(ship_cost)
+    }
+
+    pub fn path_ships(
+        &mut self,
+        paths: &mut HashMap<ShipId, VecDeque<Action>>,
+        fallback: &mut Fallback,
+        stats: &Stats,
+    ) -> Vec<Command> {
         let mut command_queue = Vec::new();
 
         let unpathed: Vec<_> = self.unpathed.drain(..).collect();
@@ -540,28 +739,62 @@ impl Timeline {
                 break
             };
 
-            self.path_ship(action, start, max_lookahead, paths);
+            self.path_ship(action, start, max_lookahead, paths, stats);
         }
 
         let ships = self.state(0).ships.clone();
+        // Only gathered when they will be logged, as it copies every path
+        let tracing = Log::enabled(Level::Trace, Category::Planner);
+        let mut traces: HashMap<ShipId, Trace> = ships.iter()
+            .filter(|_| tracing)
+            .map(|&(ship_id, (pos, _))| {
+                let mut trace = Trace::new(ship_id, pos, self.target_pos_t(ship_id, pos));
+                trace.poisoned = self.poisoned.get(&ship_id).cloned();
+                trace.cost = self.costs.get(&ship_id).cloned();
+                trace.path = paths.get(&ship_id).map(|path| path.iter().cloned().collect()).unwrap_or_default();
+                (ship_id, trace)
+            })
+            .collect();
+
         for &(ship_id, (pos, hal)) in &ships {
             if !paths.contains_key(&ship_id) {
+                if let Some(trace) = traces.get_mut(&ship_id) {
+                    trace.unpathed = true;
+                }
+
                 let target = self.target_dropoffs[&ship_id].0;
                 let can_move = hal >= self.state(0).halite(pos) / self.constants.move_cost_ratio;
                 if can_move {
-                    if pos == target {
-                        self.nav.move_away(ship_id, pos);
-                    } else {
-                        self.nav.naive_navigate(ship_id, pos, target);
-                    }
+                    let directions = {
+                        let state = self.state(0);
+                        fallback.rank(&state, &self.nav, ship_id, pos, hal, target)
+                    };
+
+                    self.nav.ranked(ship_id, pos, directions);
                 }
             }
         }
 
         let mut expected = HashMap::new();
+        let mut held = Vec::new();
         for (&ship_id, path) in paths.iter_mut() {
-            let action = path.pop_front().expect("Empty path");
             let pos = self.state(0).ship(ship_id).0;
+
+            // A move to or next to an enemy is weighed against holding still a turn
+            let contested = {
+                let state = self.state(0);
+                let next = state.normalize(pos.directional_offset(path[0].dir));
+                !path[0].dropoff && path[0].dir != Direction::Still && state.enemy_value(next).is_some()
+            };
+            let checked = stats.remaining() > Duration::from_millis(CONTEST_MIN_REMAINING_MS);
+            if contested && checked && self.holds_back(ship_id, path) {
+                Log::info(Category::Planner, format_args!("Hold:{}", ship_id.0));
+                self.nav.nav(ship_id, pos, Direction::Still);
+                held.push(ship_id);
+                continue;
+            }
+
+            let action = path.pop_front().expect("Empty path");
             if action.dropoff {
                 command_queue.push(Command::transform_ship_into_dropoff_site(ship_id))
             } else {
@@ -570,14 +803,27 @@ impl Timeline {
             }
         }
 
+        // Held ships are planned afresh next turn
+        for ship_id in held {
+            paths.remove(&ship_id);
+        }
+
         let end_game = self.state(0).end_game();
         self.nav.terminal = end_game;
 
-        for (ship_id, dir) in self.nav.collect_moves() {
+        let moves = {
+            let _span = stats.span("collect_moves");
+            self.nav.collect_moves()
+        };
+
+        for (ship_id, dir) in moves {
             let command = Command::move_ship(ship_id, dir);
             if let Some(expected) = expected.remove(&ship_id) {
                 if expected != command {
-                    Log::warn(format!("Blocked:{}", ship_id.0));
+                    Log::warn(Category::Navi, format_args!("Blocked:{}", ship_id.0));
+                    if let Some(trace) = traces.get_mut(&ship_id) {
+                        trace.blocked = true;
+                    }
                     paths.remove(&ship_id);
                 }
             }
@@ -585,6 +831,12 @@ impl Timeline {
             command_queue.push(command);
         }
 
+        let mut traces: Vec<Trace> = traces.into_values().collect();
+        traces.sort_by_key(|trace| trace.ship_id.0);
+        for trace in traces {
+            Log::trace(Category::Planner, trace);
+        }
+
         command_queue
     }
 
@@ -594,18 +846,31 @@ impl Timeline {
         start: usize,
         max_lookahead: usize,
         paths: &mut HashMap<ShipId, VecDeque<Action>>,
+        stats: &Stats,
     ) {
+        let mut span = stats.span("path_ship");
         let ship_id = initial_action.ship_id;
         let ship_pos = initial_action.pos;
         let target = self.target_pos_t(ship_id, ship_pos);
 
-        if let Some(path) = self.path(initial_action.clone(), start, target, max_lookahead) {
+        let path = self.path(initial_action.clone(), start, target, max_lookahead);
+        span.nodes(self.expansions);
+
+        if let Some(path) = path {
             let mut actions = VecDeque::new();
 
+            if let Some(last) = path.last() {
+                self.costs.insert(ship_id, last.breakdown);
+            }
+
             for (i, diff) in path.iter().enumerate() {
                 self.state(i + start).apply_merged_mut(diff);
             }
 
+            for (i, edge) in path.windows(2).enumerate() {
+                self.state(i + start + 1).record_move(edge[0].pos, edge[1].pos);
+            }
+
             for (i, edge) in path.windows(2).enumerate() {
                 let prev = &edge[0];
                 let next = &edge[1];
@@ -614,7 +879,7 @@ impl Timeline {
                 let action = Action::new(next.ship_id, dir, next.inspired, next.risk, false);
 
                 actions.push_back(action);
-                Log::log(next.pos, format!("-ship[{}:t{}:h{}]-", ship_id.0, i + start, next.halite), "yellow");
+                Log::log(Category::Planner, next.pos, format_args!("-ship[{}:t{}:h{}]-", ship_id.0, i + start, next.halite), "yellow");
             }
 
             if !actions.is_empty() {
@@ -627,7 +892,172 @@ impl Timeline {
                 }
             } 
         } else {
-            Log::error(format!("No path found for ship {}", ship_id.0));
+            Log::error(Category::Planner, format_args!("No path found for ship {}", ship_id.0));
         }
     }
 }
+
+/// Halite within the kernel around each cell, weighed down by distance
+pub fn richness(game: &Game, spills: &HashMap<Position, Spill>) -> Grid<usize> {
+    let k = KERNEL_SIZE / 2;
+    Grid::from_fn(game.map.torus, |pos| {
+        let mut sum = 0;
+        for i in 0..=KERNEL_SIZE {
+            for j in 0..=KERNEL_SIZE {
+                let x = pos.x + i - k;
+                let y = pos.y + j - k;
+                let d = ((i - k).abs() + (j - k).abs()) as usize + 1;
+
+                // Dropped halite is a short lived opportunity, so weigh it up
+                let cell = game.map.at_position(&Position { x, y });
+                let halite = if spills.contains_key(&cell.position) {
+                    cell.halite * (1 + SPILL_PRIORITY)
+                } else {
+                    cell.halite
+                };
+
+                sum += halite / d;
+            }
+        }
+        sum
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scenario::{Scenario, assert_path_starts, assert_move, assert_dropoff_within};
+    use fixture::Frames;
+
+    #[test]
+    fn loaded_ship_heads_home() {
+        let game = Scenario::new("
+            . . . . . . . .
+            . S . . a[950] . . .
+            . . . . . . . .
+        ").game();
+
+        let mut paths = HashMap::new();
+        let mut timeline = Scenario::timeline(&game, &mut paths);
+        let ship = &game.ships[&ShipId(0)];
+        let target = timeline.target_pos_t(ship.id, ship.position);
+        assert_eq!(target.0, game.players[0].shipyard.position);
+
+        let path = timeline.path(MergedAction::new(ship.id, ship.position, ship.halite), 0, target, MAX_LOOKAHEAD).unwrap();
+        assert_path_starts(&game, &path, Direction::West);
+
+        let commands = timeline.path_ships(&mut paths, &mut Fallback::new(), &Stats::new());
+        assert_move(&commands, ShipId(0), Direction::West);
+    }
+
+    #[test]
+    fn dropoff_planned_at_richest_remote_cell() {
+        // Enough ships for a second dropoff around the shipyard, a rich patch 20 cells east
+        let (width, height) = (48, 40);
+        let rich = Position { x: 25, y: 20 };
+        let map: String = (0..height).map(|y| {
+            let row: String = (0..width).map(|x| {
+                let (dx, dy) = ((x - rich.x).abs(), (y - rich.y).abs());
+                match (x, y) {
+                    (5, 20) => 'S',
+                    (3..=7, 18..=22) => 'a',
+                    _ if dx <= 2 && dy <= 2 => '9',
+                    _ => '.',
+                }
+            }).collect();
+            row + "\n"
+        }).collect();
+
+        let game = Scenario::new(&map).game();
+        assert_eq!(game.ships.len(), 24);
+
+        let mut paths = HashMap::new();
+        let mut timeline = Scenario::timeline(&game, &mut paths);
+        timeline.make_dropoff(&mut paths, &Stats::new());
+
+        assert_dropoff_within(&game, &paths, rich, 30);
+    }
+
+    #[test]
+    fn builder_losses_count_enemies_on_the_way() {
+        let losses = |map: &str| {
+            let mut game = Scenario::new(map).game();
+            // Worth an enemy staying put to mine
+            game.map.at_position_mut(&Position { x: 4, y: 1 }).halite = 900;
+
+            let timeline = Scenario::timeline(&game, &mut HashMap::new());
+            let ship = &game.ships[&ShipId(0)];
+            let path: Vec<MergedAction> = (1..5).map(|x| MergedAction::new(ship.id, Position { x, y: 1 }, ship.halite)).collect();
+            timeline.builder_losses(0, &path)
+        };
+
+        assert_eq!(losses("
+            . . . . . . .
+            . a . . . . .
+            . . . . . . .
+            . S . . . . S[1]
+        "), 0.0);
+
+        assert!(losses("
+            . . . . . . .
+            . a . . b . .
+            . . . . . . .
+            . S . . . . S[1]
+        ") > MAX_BUILDER_LOSSES);
+    }
+
+    #[test]
+    fn contested_ship_holds_back_from_a_likely_crash() {
+        let holds = |map: &str| {
+            let game = Scenario::new(map).game();
+            let timeline = Scenario::timeline(&game, &mut HashMap::new());
+            let ship_id = game.players[0].ship_ids[0];
+            let path: VecDeque<Action> = (0..CONTEST_HORIZON)
+                .map(|_| Action::new(ship_id, Direction::East, false, true, false))
+                .collect();
+            timeline.holds_back(ship_id, &path)
+        };
+
+        assert!(!holds("
+            . . . . . . .
+            . a . . . . .
+            . . . . . . .
+            . . . . . . .
+            . S . . . . S[1]
+        "));
+
+        // Loaded enemy heading home crosses the ship's next cell
+        assert!(holds("
+            . . b[900] . . . .
+            . a . . . . .
+            . . . . . . .
+            . . S[1] . . . .
+            . . . . . . .
+            . . . . . . .
+            . . . . . S .
+        "));
+    }
+
+    #[test]
+    fn unissued_spawn_releases_the_shipyard() {
+        let game = Scenario::new("
+            . . . . .
+            . S . a .
+            . . . . .
+        ").game();
+        let shipyard = game.players[0].shipyard.position;
+
+        let mut paths = HashMap::new();
+        let mut timeline = Scenario::timeline(&game, &mut paths);
+        assert!(timeline.reserve_spawn());
+        assert_eq!(timeline.state(1).taken[shipyard], Some(timeline.spawn_action.ship_id));
+        assert!(timeline.nav.traffic.is_reserved(&shipyard));
+
+        // Halite set aside for a dropoff leaves nothing for the ship
+        let bank = timeline.state(0).halite;
+        timeline.save = bank;
+        assert!(!timeline.spawn_ship());
+        assert_eq!(timeline.state(1).taken[shipyard], None);
+        assert!(!timeline.nav.traffic.is_reserved(&shipyard));
+    }
+}
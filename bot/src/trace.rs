@@ -0,0 +1,121 @@
+use hlt::*;
+use action::{Action, Breakdown};
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+/// Why a ship moved as it did on one turn, logged at trace level as `key=value` pairs
+#[derive(Clone)]
+pub struct Trace {
+    pub ship_id: ShipId,
+    pub pos: Position,
+    /// Dropoff the ship is heading for and the turn it is available from
+    pub target: (Position, usize),
+    /// Actions the ship is following from this turn on
+    pub path: Vec<Action>,
+    /// Cost of the path when it was planned this turn
+    pub cost: Option<Breakdown>,
+    /// Timestep at which the previous path could no longer be followed
+    pub poisoned: Option<usize>,
+    /// No path was found, so the fallback chose the move
+    pub unpathed: bool,
+    /// The navigator sent the ship somewhere other than its path
+    pub blocked: bool,
+}
+
+impl Trace {
+    pub fn new(ship_id: ShipId, pos: Position, target: (Position, usize)) -> Trace {
+        Trace {
+            ship_id,
+            pos,
+            target,
+            path: Vec::new(),
+            cost: None,
+            poisoned: None,
+            unpathed: false,
+            blocked: false,
+        }
+    }
+
+    fn status(&self) -> String {
+        let mut status = Vec::new();
+
+        match self.poisoned {
+            Some(0) => status.push("poisoned".to_string()),
+            Some(t) => status.push(format!("truncated@{}", t)),
+            None => (),
+        }
+
+        if self.cost.is_some() {
+            status.push("planned".to_string());
+        } else if !self.path.is_empty() {
+            status.push("following".to_string());
+        }
+
+        if self.unpathed {
+            status.push("unpathed".to_string());
+        }
+
+        if self.blocked {
+            status.push("blocked".to_string());
+        }
+
+        status.join(",")
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let (target, t) = self.target;
+        write!(f, "ship={} pos={},{} target={},{}@{} status={}",
+               self.ship_id.0, self.pos.x, self.pos.y, target.x, target.y, t, self.status())?;
+
+        let path: String = self.path.iter()
+            .map(|action| if action.dropoff { 'c' } else { action.dir.get_char_encoding() })
+            .collect();
+        write!(f, " path={}", if path.is_empty() { "-" } else { &path })?;
+
+        if let Some(cost) = self.cost {
+            write!(f, " cost=moves:{},mining:{},risk:{},end_game:{}", cost.moves, cost.mining, cost.risk, cost.end_game)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Turn and ship id of a trace line in the diagnostic log
+pub fn parse_line(line: &str) -> Option<(usize, ShipId)> {
+    let mut words = line.split_whitespace();
+    let turn = words.next()?.parse().ok()?;
+
+    if words.next()? != "TRACE" {
+        return None;
+    }
+
+    let ship_id = words.nth(1)?.strip_prefix("ship=")?.parse().ok()?;
+    Some((turn, ShipId(ship_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_round_trips_through_log_line() {
+        let mut trace = Trace::new(ShipId(7), Position { x: 3, y: 4 }, (Position { x: 8, y: 16 }, 2));
+        trace.poisoned = Some(5);
+        trace.path = vec![
+            Action::new(ShipId(7), Direction::East, false, false, false),
+            Action::new(ShipId(7), Direction::Still, false, false, false),
+        ];
+        trace.cost = Some(Breakdown { moves: 12, mining: -300, risk: 40, end_game: 0 });
+
+        let message = trace.to_string();
+        assert_eq!(message, "ship=7 pos=3,4 target=8,16@2 status=truncated@5,planned path=eo \
+                             cost=moves:12,mining:-300,risk:40,end_game:0");
+
+        let line = format!("{:>3} {:<5} {:<8} {}", 12, "TRACE", "planner", message);
+        assert_eq!(parse_line(&line), Some((12, ShipId(7))));
+        assert_eq!(parse_line(" 12 INFO  planner  ship=7"), None);
+    }
+}